@@ -0,0 +1,162 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Built-in fixture used when no `--fixture` is given
+const DEFAULT_FIXTURE: &str = include_str!("../../../fixtures/demo.json");
+
+/// How the mock announces itself to a freshly connected client
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMode {
+    /// Send `authenticated` + `ready` right away, like a logged-in service
+    #[default]
+    Ready,
+    /// Send a `qr` event first and authenticate after `qr_delay_ms`
+    Qr,
+}
+
+/// Chats, messages and scripted events served by the mock
+#[derive(Debug, Deserialize)]
+pub struct Fixture {
+    #[serde(default)]
+    pub auth: AuthMode,
+
+    #[serde(default = "default_qr")]
+    pub qr: String,
+
+    #[serde(default = "default_qr_delay_ms")]
+    pub qr_delay_ms: u64,
+
+    /// Wait before answering each request, like a slow service
+    #[serde(default)]
+    pub response_delay_ms: u64,
+
+    /// Raw chat objects, sent as-is in `getChats`
+    #[serde(default)]
+    pub chats: Vec<Value>,
+
+    /// Raw message objects, served per `chat_id` in `getMessages`
+    #[serde(default)]
+    pub messages: Vec<Value>,
+
+    /// Steps played once the first client connects
+    #[serde(default)]
+    pub script: Vec<ScriptStep>,
+
+    /// Attachments by message id, taken from each message's `media` object
+    #[serde(skip)]
+    pub media: HashMap<String, MediaFile>,
+
+    /// Profile pictures by chat id, taken from each chat's `avatar` object
+    #[serde(skip)]
+    pub avatars: HashMap<String, MediaFile>,
+}
+
+/// Attachment served by `downloadMedia`, or profile picture served by `getProfilePicture`
+#[derive(Debug, Clone, Deserialize)]
+pub struct MediaFile {
+    pub mimetype: String,
+
+    #[serde(default)]
+    pub filename: Option<String>,
+
+    /// Base64 in the fixture, raw bytes once loaded
+    #[serde(deserialize_with = "decode_base64")]
+    pub data: Vec<u8>,
+}
+
+fn decode_base64<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    let encoded = String::deserialize(deserializer)?;
+    base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(serde::de::Error::custom)
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScriptStep {
+    /// Delay since the previous step
+    #[serde(default)]
+    pub after_ms: u64,
+
+    #[serde(flatten)]
+    pub action: ScriptAction,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ScriptAction {
+    /// Deliver an incoming message (broadcast as a `message` event)
+    Message { message: Value },
+    /// Broadcast an arbitrary event
+    Event {
+        event: String,
+        #[serde(default)]
+        data: Option<Value>,
+    },
+    /// Close every client connection, as if the service went away
+    Disconnect,
+}
+
+fn default_qr() -> String {
+    "zaptui-mock-service".to_string()
+}
+
+fn default_qr_delay_ms() -> u64 {
+    5000
+}
+
+impl Fixture {
+    /// Load a fixture from disk, or the built-in one when no path is given
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let content = match path {
+            Some(path) => std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read fixture {}", path.display()))?,
+            None => DEFAULT_FIXTURE.to_string(),
+        };
+
+        let mut fixture: Fixture = serde_json::from_str(&content)
+            .context("Failed to parse fixture")?;
+
+        // Timestamps <= 0 are relative to startup, so fixtures never go stale
+        let now = chrono::Utc::now().timestamp();
+        for value in fixture.chats.iter_mut().chain(fixture.messages.iter_mut()) {
+            resolve_timestamp(value, now);
+        }
+
+        // Attachments stay out of the message objects, like on the real service
+        for message in &mut fixture.messages {
+            let Some(media) = message.as_object_mut().and_then(|obj| obj.remove("media")) else {
+                continue;
+            };
+            let id = message["id"].as_str().unwrap_or_default().to_string();
+            let media: MediaFile = serde_json::from_value(media)
+                .with_context(|| format!("Invalid media for message {}", id))?;
+            fixture.media.insert(id, media);
+        }
+        for chat in &mut fixture.chats {
+            let Some(avatar) = chat.as_object_mut().and_then(|obj| obj.remove("avatar")) else {
+                continue;
+            };
+            let id = chat["id"].as_str().unwrap_or_default().to_string();
+            let avatar: MediaFile = serde_json::from_value(avatar)
+                .with_context(|| format!("Invalid avatar for chat {}", id))?;
+            fixture.avatars.insert(id, avatar);
+        }
+
+        Ok(fixture)
+    }
+}
+
+/// Turn a relative (<= 0) or missing `timestamp` into an absolute one
+pub fn resolve_timestamp(value: &mut Value, now: i64) {
+    if let Some(obj) = value.as_object_mut() {
+        let ts = obj.get("timestamp").and_then(Value::as_i64).unwrap_or(0);
+        if ts <= 0 {
+            obj.insert("timestamp".to_string(), Value::from(now + ts));
+        }
+    }
+}
@@ -0,0 +1,574 @@
+//! Mock WhatsApp service for offline development and CI
+//!
+//! Speaks the same WebSocket protocol as `whatsapp-service/server.js`, but
+//! serves chats and messages from a JSON fixture instead of a real phone.
+
+use anyhow::Result;
+use base64::Engine;
+use clap::Parser;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, Mutex};
+use tokio_tungstenite::{accept_async, tungstenite::Message as WsMessage};
+use uuid::Uuid;
+
+mod fixture;
+
+use fixture::{AuthMode, Fixture, MediaFile, ScriptAction, ScriptStep};
+
+/// Mock WhatsApp service speaking the zaptui JSON protocol
+#[derive(Parser, Debug)]
+#[command(name = "zaptui-mock-service")]
+#[command(version, about = "Mock WhatsApp service for running zaptui without a phone", long_about = None)]
+struct Cli {
+    /// Port to listen on
+    #[arg(long, default_value_t = 8080)]
+    port: u16,
+
+    /// Fixture file with chats, messages and scripted events (built-in demo if omitted)
+    #[arg(long)]
+    fixture: Option<PathBuf>,
+}
+
+struct State {
+    chats: Vec<Value>,
+    messages: Vec<Value>,
+    clients: HashMap<u64, mpsc::UnboundedSender<WsMessage>>,
+    next_client_id: u64,
+    authenticated: bool,
+    script: Option<Vec<ScriptStep>>,  // Taken when the first client connects
+    uploads: HashMap<String, Upload>,  // In-progress sendMedia uploads by upload id
+    sent: HashSet<String>,  // sendMessage client ids already delivered, so retries are no-ops
+    media: HashMap<String, MediaFile>,  // Attachments by message id
+    avatars: HashMap<String, MediaFile>,  // Profile pictures by chat id
+    downloads: HashMap<String, Vec<u8>>,  // Media being fetched with downloadMediaChunk, by download id
+}
+
+/// Media upload assembled from `sendMediaChunk` requests
+struct Upload {
+    client_id: u64,  // Connection that started it, dropped with it
+    start: Value,  // sendMediaStart params
+    data: Vec<u8>,
+}
+
+/// Uploads nobody finishes are dropped after this long
+const UPLOAD_TIMEOUT: tokio::time::Duration = tokio::time::Duration::from_secs(10 * 60);
+
+struct MockService {
+    qr: String,
+    qr_delay_ms: u64,
+    response_delay_ms: u64,
+    state: Mutex<State>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let fixture = Fixture::load(cli.fixture.as_deref())?;
+    log::info!(
+        "Loaded fixture: {} chats, {} messages, {} script steps",
+        fixture.chats.len(),
+        fixture.messages.len(),
+        fixture.script.len()
+    );
+
+    let service = Arc::new(MockService {
+        qr: fixture.qr,
+        qr_delay_ms: fixture.qr_delay_ms,
+        response_delay_ms: fixture.response_delay_ms,
+        state: Mutex::new(State {
+            chats: fixture.chats,
+            messages: fixture.messages,
+            clients: HashMap::new(),
+            next_client_id: 0,
+            authenticated: fixture.auth == AuthMode::Ready,
+            script: Some(fixture.script),
+            uploads: HashMap::new(),
+            sent: HashSet::new(),
+            media: fixture.media,
+            avatars: fixture.avatars,
+            downloads: HashMap::new(),
+        }),
+    });
+
+    let listener = TcpListener::bind(("127.0.0.1", cli.port)).await?;
+    log::info!("Mock WhatsApp service listening on ws://localhost:{}", cli.port);
+
+    loop {
+        let (stream, addr) = listener.accept().await?;
+        log::info!("New client connected from {}", addr);
+        tokio::spawn(Arc::clone(&service).handle_connection(stream));
+    }
+}
+
+impl MockService {
+    async fn handle_connection(self: Arc<Self>, stream: TcpStream) {
+        let ws_stream = match accept_async(stream).await {
+            Ok(ws) => ws,
+            Err(e) => {
+                log::error!("WebSocket handshake failed: {}", e);
+                return;
+            }
+        };
+
+        let (mut write, mut read) = ws_stream.split();
+        let (tx, mut rx) = mpsc::unbounded_channel::<WsMessage>();
+
+        let (client_id, authenticated, script) = {
+            let mut state = self.state.lock().await;
+            let id = state.next_client_id;
+            state.next_client_id += 1;
+            state.clients.insert(id, tx.clone());
+            (id, state.authenticated, state.script.take())
+        };
+
+        // Spawn task to write messages
+        tokio::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                let is_close = matches!(msg, WsMessage::Close(_));
+                if write.send(msg).await.is_err() || is_close {
+                    break;
+                }
+            }
+        });
+
+        // Send initial state to new client
+        if authenticated {
+            send_event(&tx, "ready", None);
+            send_event(&tx, "authenticated", None);
+        } else {
+            send_event(&tx, "qr", Some(json!(self.qr)));
+            tokio::spawn(Arc::clone(&self).authenticate_later());
+        }
+
+        if let Some(script) = script {
+            tokio::spawn(Arc::clone(&self).run_script(script));
+        }
+
+        while let Some(msg) = read.next().await {
+            match msg {
+                Ok(WsMessage::Text(text)) => {
+                    // A disconnect meanwhile loses the response, as with the real service
+                    if self.response_delay_ms > 0 {
+                        tokio::time::sleep(tokio::time::Duration::from_millis(self.response_delay_ms)).await;
+                    }
+                    let response = match serde_json::from_str::<Value>(&text) {
+                        Ok(request) => self.handle_request(&request, client_id).await,
+                        Err(e) => json!({ "id": "unknown", "error": e.to_string() }),
+                    };
+                    let _ = tx.send(WsMessage::Text(response.to_string()));
+                }
+                Ok(WsMessage::Close(_)) | Err(_) => break,
+                _ => {}
+            }
+        }
+
+        log::info!("Client {} disconnected", client_id);
+        let mut state = self.state.lock().await;
+        state.clients.remove(&client_id);
+        state.uploads.retain(|_, upload| upload.client_id != client_id);
+    }
+
+    /// Simulate the user scanning the QR code
+    async fn authenticate_later(self: Arc<Self>) {
+        tokio::time::sleep(tokio::time::Duration::from_millis(self.qr_delay_ms)).await;
+
+        let mut state = self.state.lock().await;
+        if state.authenticated {
+            return;
+        }
+        state.authenticated = true;
+        log::info!("Simulated QR scan, client authenticated");
+        broadcast(&state, "authenticated", None);
+        broadcast(&state, "ready", None);
+    }
+
+    async fn run_script(self: Arc<Self>, script: Vec<ScriptStep>) {
+        for step in script {
+            tokio::time::sleep(tokio::time::Duration::from_millis(step.after_ms)).await;
+
+            let mut state = self.state.lock().await;
+            match step.action {
+                ScriptAction::Message { message } => {
+                    let message = deliver_message(&mut state, message);
+                    log::info!("Script: delivered message to {}", message["chat_id"]);
+                }
+                ScriptAction::Event { event, data } => {
+                    log::info!("Script: broadcasting {} event", event);
+                    broadcast(&state, &event, data);
+                }
+                ScriptAction::Disconnect => {
+                    log::info!("Script: disconnecting {} clients", state.clients.len());
+                    for tx in state.clients.values() {
+                        let _ = tx.send(WsMessage::Close(None));
+                    }
+                    state.clients.clear();
+                }
+            }
+        }
+    }
+
+    /// Walk a sent message through delivered and read, like a recipient would
+    async fn simulate_receipts(self: Arc<Self>, message: Value) {
+        for (delay_ms, ack) in [(1000, 2), (2000, 3)] {
+            tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms)).await;
+
+            let mut state = self.state.lock().await;
+            if let Some(stored) = state.messages.iter_mut().find(|m| m["id"] == message["id"]) {
+                stored["ack"] = json!(ack);
+            }
+            broadcast(&state, "message_ack", Some(json!({
+                "id": message["id"],
+                "chat_id": message["chat_id"],
+                "ack": ack,
+            })));
+        }
+    }
+
+    /// Drop an upload that's still unfinished after `UPLOAD_TIMEOUT`
+    async fn expire_upload(self: Arc<Self>, upload_id: String) {
+        tokio::time::sleep(UPLOAD_TIMEOUT).await;
+        if self.state.lock().await.uploads.remove(&upload_id).is_some() {
+            log::info!("Dropped abandoned upload {}", upload_id);
+        }
+    }
+
+    async fn handle_request(self: &Arc<Self>, request: &Value, client_id: u64) -> Value {
+        let id = request["id"].as_str().unwrap_or("unknown");
+        let method = request["method"].as_str().unwrap_or_default();
+        let params = &request["params"];
+        log::debug!("Request {}: {} {}", id, method, params);
+
+        let mut state = self.state.lock().await;
+        let result = match method {
+            "getChats" => Ok(Value::Array(state.chats.clone())),
+
+            "getMessages" => {
+                let chat_id = params["chatId"].as_str().unwrap_or_default();
+                let limit = params["limit"].as_u64().unwrap_or(50) as usize;
+                let mut messages: Vec<Value> = state.messages.iter()
+                    .filter(|m| m["chat_id"] == chat_id)
+                    .cloned()
+                    .collect();
+                messages.sort_by_key(|m| m["timestamp"].as_i64().unwrap_or(0));
+                let skip = messages.len().saturating_sub(limit);
+                Ok(Value::Array(messages.split_off(skip)))
+            }
+
+            "sendMessage" if params["clientId"].as_str().is_some_and(|id| state.sent.contains(id)) => {
+                Ok(json!({ "success": true }))
+            }
+
+            "sendMessage" => {
+                let chat_id = params["chatId"].as_str().unwrap_or_default();
+                if state.chats.iter().any(|c| c["id"] == chat_id) {
+                    let quoted = state.messages.iter()
+                        .find(|m| params["quotedMessageId"].is_string() && m["id"] == params["quotedMessageId"])
+                        .map(|m| json!({
+                            "id": m["id"],
+                            "body": m["body"],
+                            "from_me": m["from_me"],
+                            "sender": m["sender"],
+                        }));
+                    let message = deliver_message(&mut state, json!({
+                        "chat_id": chat_id,
+                        "body": params["text"],
+                        "from_me": true,
+                        "ack": 1,
+                        "quoted": quoted,
+                    }));
+                    if let Some(id) = params["clientId"].as_str() {
+                        state.sent.insert(id.to_string());
+                    }
+                    tokio::spawn(Arc::clone(self).simulate_receipts(message));
+                    Ok(json!({ "success": true }))
+                } else {
+                    Err(format!("Chat not found: {}", chat_id))
+                }
+            }
+
+            "sendMediaStart" => {
+                let upload_id = params["uploadId"].as_str().unwrap_or_default();
+                if state.chats.iter().any(|c| c["id"] == params["chatId"]) {
+                    state.uploads.insert(upload_id.to_string(), Upload { client_id, start: params.clone(), data: Vec::new() });
+                    tokio::spawn(Arc::clone(self).expire_upload(upload_id.to_string()));
+                    Ok(json!({ "success": true }))
+                } else {
+                    Err(format!("Chat not found: {}", params["chatId"]))
+                }
+            }
+
+            "sendMediaChunk" => {
+                let upload_id = params["uploadId"].as_str().unwrap_or_default();
+                let chunk = base64::engine::general_purpose::STANDARD.decode(params["data"].as_str().unwrap_or_default());
+                let result = match (state.uploads.get_mut(upload_id), chunk) {
+                    (Some(upload), Ok(chunk)) if params["offset"].as_u64() == Some(upload.data.len() as u64) => {
+                        upload.data.extend(chunk);
+                        Ok(json!({ "received": upload.data.len() }))
+                    }
+                    (Some(_), Ok(_)) => Err("Chunk out of order".to_string()),
+                    (Some(_), Err(e)) => Err(format!("Invalid chunk: {}", e)),
+                    (None, _) => Err(format!("Unknown upload: {}", upload_id)),
+                };
+                // The client gives up on a failed upload, so don't keep its chunks
+                if result.is_err() {
+                    state.uploads.remove(upload_id);
+                }
+                result
+            }
+
+            "sendMediaFinish" => {
+                let upload_id = params["uploadId"].as_str().unwrap_or_default();
+                match state.uploads.remove(upload_id) {
+                    Some(upload) => {
+                        let start = upload.start;
+                        log::info!("Received {} ({} bytes)", start["filename"], upload.data.len());
+                        let media_type = start["mimetype"].as_str()
+                            .and_then(|m| m.split('/').next())
+                            .filter(|kind| matches!(*kind, "image" | "video" | "audio"))
+                            .unwrap_or("document");
+                        let quoted = state.messages.iter()
+                            .find(|m| start["quotedMessageId"].is_string() && m["id"] == start["quotedMessageId"])
+                            .map(|m| json!({
+                                "id": m["id"],
+                                "body": m["body"],
+                                "from_me": m["from_me"],
+                                "sender": m["sender"],
+                            }));
+                        let message = deliver_message(&mut state, json!({
+                            "chat_id": start["chatId"],
+                            "body": start["caption"],
+                            "from_me": true,
+                            "has_media": true,
+                            "media_type": media_type,
+                            "ack": 1,
+                            "quoted": quoted,
+                        }));
+                        if let Some(id) = message["id"].as_str() {
+                            state.media.insert(id.to_string(), MediaFile {
+                                mimetype: start["mimetype"].as_str().unwrap_or("application/octet-stream").to_string(),
+                                filename: start["filename"].as_str().map(str::to_string),
+                                data: upload.data,
+                            });
+                        }
+                        tokio::spawn(Arc::clone(self).simulate_receipts(message));
+                        Ok(json!({ "success": true }))
+                    }
+                    None => Err(format!("Unknown upload: {}", upload_id)),
+                }
+            }
+
+            "react" => {
+                let emoji = params["emoji"].as_str().unwrap_or_default();
+                match state.messages.iter_mut().find(|m| m["id"] == params["messageId"]) {
+                    Some(message) => {
+                        let mut reactions: Vec<Value> = message["reactions"].as_array().cloned().unwrap_or_default();
+                        reactions.retain(|r| r["sender_id"] != "me");
+                        if !emoji.is_empty() {
+                            reactions.push(json!({ "emoji": emoji, "sender_id": "me", "from_me": true }));
+                        }
+                        message["reactions"] = json!(reactions);
+
+                        let update = json!({
+                            "message_id": message["id"],
+                            "chat_id": message["chat_id"],
+                            "emoji": emoji,
+                            "sender_id": "me",
+                            "from_me": true,
+                        });
+                        broadcast(&state, "reaction", Some(update));
+                        Ok(json!({ "success": true }))
+                    }
+                    None => Err(format!("Message not found: {}", params["messageId"])),
+                }
+            }
+
+            "editMessage" => {
+                match state.messages.iter_mut().find(|m| m["id"] == params["messageId"]) {
+                    Some(message) if message["from_me"] == json!(true) => {
+                        message["body"] = params["text"].clone();
+                        message["edited"] = json!(true);
+                        let edit = json!({
+                            "id": message["id"],
+                            "chat_id": message["chat_id"],
+                            "body": message["body"],
+                        });
+                        broadcast(&state, "message_edit", Some(edit));
+                        Ok(json!({ "success": true }))
+                    }
+                    Some(_) => Err("Only your own messages can be edited".to_string()),
+                    None => Err(format!("Message not found: {}", params["messageId"])),
+                }
+            }
+
+            "deleteMessage" => {
+                let everyone = params["everyone"].as_bool().unwrap_or(false);
+                match state.messages.iter().position(|m| m["id"] == params["messageId"]) {
+                    Some(index) if everyone && state.messages[index]["from_me"] != json!(true) => {
+                        Err("Only your own messages can be deleted for everyone".to_string())
+                    }
+                    Some(index) => {
+                        let revoke = json!({
+                            "id": state.messages[index]["id"],
+                            "chat_id": state.messages[index]["chat_id"],
+                            "for_everyone": everyone,
+                        });
+                        if everyone {
+                            state.messages[index]["body"] = json!("");
+                            state.messages[index]["deleted"] = json!(true);
+                        } else {
+                            state.messages.remove(index);
+                        }
+                        broadcast(&state, "message_revoke", Some(revoke));
+                        Ok(json!({ "success": true }))
+                    }
+                    None => Err(format!("Message not found: {}", params["messageId"])),
+                }
+            }
+
+            "forwardMessages" => {
+                let ids: Vec<&Value> = params["messageIds"].as_array().map(|ids| ids.iter().collect()).unwrap_or_default();
+                let chat_ids: Vec<&Value> = params["chatIds"].as_array().map(|ids| ids.iter().collect()).unwrap_or_default();
+                let originals: Vec<Value> = ids.iter()
+                    .filter_map(|id| state.messages.iter().find(|m| m["id"] == **id).cloned())
+                    .collect();
+
+                if let Some(missing) = ids.iter().find(|id| !originals.iter().any(|m| m["id"] == ***id)) {
+                    Err(format!("Message not found: {}", missing))
+                } else if let Some(missing) = chat_ids.iter().find(|id| !state.chats.iter().any(|c| c["id"] == ***id)) {
+                    Err(format!("Chat not found: {}", missing))
+                } else {
+                    for chat_id in chat_ids {
+                        for original in &originals {
+                            let message = deliver_message(&mut state, json!({
+                                "chat_id": chat_id,
+                                "body": original["body"],
+                                "from_me": true,
+                                "ack": 1,
+                                "has_media": original["has_media"].as_bool().unwrap_or(false),
+                                "media_type": original["media_type"],
+                                "forwarded": true,
+                            }));
+                            // The copy downloads like the original
+                            if let Some(media) = original["id"].as_str().and_then(|id| state.media.get(id)).cloned() {
+                                let id = message["id"].as_str().unwrap_or_default().to_string();
+                                state.media.insert(id, media);
+                            }
+                            tokio::spawn(Arc::clone(self).simulate_receipts(message));
+                        }
+                    }
+                    Ok(json!({ "success": true }))
+                }
+            }
+
+            "downloadMedia" => {
+                let message_id = params["messageId"].as_str().unwrap_or_default();
+                match state.media.get(message_id).cloned() {
+                    Some(media) => {
+                        let download_id = Uuid::new_v4().to_string();
+                        let info = json!({
+                            "downloadId": download_id,
+                            "size": media.data.len(),
+                            "mimetype": media.mimetype,
+                            "filename": media.filename,
+                        });
+                        state.downloads.insert(download_id, media.data);
+                        Ok(info)
+                    }
+                    None => Err(format!("No media for message {}", message_id)),
+                }
+            }
+
+            "downloadMediaChunk" => {
+                let download_id = params["downloadId"].as_str().unwrap_or_default();
+                let offset = params["offset"].as_u64().unwrap_or(0) as usize;
+                let length = params["length"].as_u64().unwrap_or(256 * 1024) as usize;
+                match state.downloads.get(download_id) {
+                    Some(data) => {
+                        let end = (offset + length).min(data.len());
+                        let chunk = base64::engine::general_purpose::STANDARD.encode(&data[offset.min(end)..end]);
+                        if end == data.len() {
+                            state.downloads.remove(download_id);
+                        }
+                        Ok(json!({ "data": chunk }))
+                    }
+                    None => Err(format!("Unknown download: {}", download_id)),
+                }
+            }
+
+            "searchMessages" => {
+                let query = params["query"].as_str().unwrap_or_default().to_lowercase();
+                let limit = params["limit"].as_u64().unwrap_or(50) as usize;
+                let mut found: Vec<Value> = state.messages.iter()
+                    .filter(|m| m["body"].as_str().is_some_and(|body| body.to_lowercase().contains(&query)))
+                    .cloned()
+                    .collect();
+                found.sort_by_key(|m| std::cmp::Reverse(m["timestamp"].as_i64().unwrap_or(0)));
+                found.truncate(limit);
+                Ok(Value::Array(found))
+            }
+
+            "getProfilePicture" => {
+                let chat_id = params["chatId"].as_str().unwrap_or_default();
+                match state.avatars.get(chat_id) {
+                    Some(avatar) => Ok(json!({
+                        "data": base64::engine::general_purpose::STANDARD.encode(&avatar.data),
+                        "mimetype": avatar.mimetype,
+                    })),
+                    None => Ok(json!({ "data": null })),
+                }
+            }
+
+            _ => Err(format!("Unknown method: {}", method)),
+        };
+
+        match result {
+            Ok(result) => json!({ "id": id, "result": result }),
+            Err(error) => json!({ "id": id, "error": error }),
+        }
+    }
+}
+
+/// Store a message, bump its chat and broadcast it as a `message` event
+fn deliver_message(state: &mut State, mut message: Value) -> Value {
+    if let Some(obj) = message.as_object_mut() {
+        obj.entry("id").or_insert_with(|| json!(format!("mock-{}", Uuid::new_v4())));
+        obj.entry("from_me").or_insert(json!(false));
+        obj.entry("body").or_insert(json!(""));
+    }
+    fixture::resolve_timestamp(&mut message, chrono::Utc::now().timestamp());
+
+    if let Some(chat) = state.chats.iter_mut().find(|c| c["id"] == message["chat_id"]) {
+        chat["timestamp"] = message["timestamp"].clone();
+        chat["last_message"] = message["body"].clone();
+        if message["from_me"] != json!(true) {
+            let unread = chat["unread_count"].as_u64().unwrap_or(0);
+            chat["unread_count"] = json!(unread + 1);
+        }
+    }
+
+    state.messages.push(message.clone());
+    broadcast(state, "message", Some(message.clone()));
+    message
+}
+
+fn send_event(tx: &mpsc::UnboundedSender<WsMessage>, event: &str, data: Option<Value>) {
+    let payload = match data {
+        Some(data) => json!({ "event": event, "data": data }),
+        None => json!({ "event": event }),
+    };
+    let _ = tx.send(WsMessage::Text(payload.to_string()));
+}
+
+fn broadcast(state: &State, event: &str, data: Option<Value>) {
+    for tx in state.clients.values() {
+        send_event(tx, event, data.clone());
+    }
+}
@@ -27,11 +27,16 @@ pub struct MediaConfig {
     #[serde(default = "default_download_path")]
     pub download_path: String,
     
-    #[serde(default = "default_true")]
+    /// Off by default: anyone in a chat could otherwise fill the disk
+    #[serde(default)]
     pub auto_download: bool,
     
     #[serde(default = "default_true")]
     pub kitty_graphics: bool,
+    
+    /// "auto", "kitty", "sixel" or "halfblocks"
+    #[serde(default = "default_image_protocol")]
+    pub image_protocol: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,6 +49,17 @@ pub struct UiConfig {
     
     #[serde(default = "default_true")]
     pub relative_timestamps: bool,
+    
+    /// "local" or an IANA name like "Europe/Berlin"
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    
+    /// strftime formats for message times and day separators
+    #[serde(default = "default_time_format")]
+    pub time_format: String,
+    
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -64,14 +80,35 @@ fn default_session_path() -> String {
     "./.wwebjs_auth".to_string()
 }
 
+/// The XDG download dir (e.g. ~/Downloads/zaptui), else the data dir
 fn default_download_path() -> String {
-    "./media".to_string()
+    dirs::download_dir()
+        .map(|dir| dir.join("zaptui"))
+        .or_else(|| dirs::data_dir().map(|dir| dir.join("zaptui").join("media")))
+        .map(|dir| dir.display().to_string())
+        .unwrap_or_else(|| "./media".to_string())
+}
+
+fn default_image_protocol() -> String {
+    "auto".to_string()
 }
 
 fn default_theme() -> String {
     "dark".to_string()
 }
 
+fn default_timezone() -> String {
+    "local".to_string()
+}
+
+pub fn default_time_format() -> String {
+    "%H:%M".to_string()
+}
+
+pub fn default_date_format() -> String {
+    "%a %d %b %Y".to_string()
+}
+
 fn default_true() -> bool {
     true
 }
@@ -110,6 +147,11 @@ impl Config {
         Ok(config)
     }
     
+    /// Where user themes live: `themes/` next to config.toml
+    pub fn themes_dir(&self) -> PathBuf {
+        self.config_path.parent().unwrap_or(Path::new(".")).join("themes")
+    }
+    
     /// Migrate from old JavaScript config.json format
     #[allow(dead_code)]
     fn migrate_from_json(json_path: &Path) -> Result<Self> {
@@ -141,13 +183,17 @@ impl Config {
             },
             media: MediaConfig {
                 download_path: old.download_path.unwrap_or_else(default_download_path),
-                auto_download: old.download_media.unwrap_or(true),
+                auto_download: old.download_media.unwrap_or(false),
                 kitty_graphics: true,
+                image_protocol: default_image_protocol(),
             },
             ui: UiConfig {
                 theme: default_theme(),
                 show_avatars: true,
                 relative_timestamps: true,
+                timezone: default_timezone(),
+                time_format: default_time_format(),
+                date_format: default_date_format(),
             },
             notifications: NotificationConfig {
                 enabled: old.notifications.unwrap_or(true),
@@ -178,13 +224,17 @@ impl Default for Config {
             },
             media: MediaConfig {
                 download_path: default_download_path(),
-                auto_download: true,
+                auto_download: false,
                 kitty_graphics: true,
+                image_protocol: default_image_protocol(),
             },
             ui: UiConfig {
                 theme: default_theme(),
                 show_avatars: true,
                 relative_timestamps: true,
+                timezone: default_timezone(),
+                time_format: default_time_format(),
+                date_format: default_date_format(),
             },
             notifications: NotificationConfig {
                 enabled: true,
@@ -0,0 +1,9 @@
+//! ZapTUI's app, backends and local storage, shared by the `zaptui` binary
+//! and the integration tests
+
+pub mod config;
+pub mod media;
+pub mod outbox;
+pub mod store;
+pub mod ui;
+pub mod whatsapp;
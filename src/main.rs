@@ -1,37 +1,40 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Parser;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event, KeyCode,
+        KeyEventKind, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
     backend::CrosstermBackend,
     Terminal,
 };
-use std::io;
+use std::io::{self, Write};
+use std::sync::Arc;
 use tokio::sync::mpsc;
 
-mod config;
-mod ui;
-mod whatsapp;
-
-use config::Config;
-use ui::App;
-use whatsapp::{WhatsAppClient, WhatsAppEvent};
+use zaptui::config::Config;
+use zaptui::store::MessageStore;
+use zaptui::ui::{self, App, ImageProtocol};
+use zaptui::whatsapp::{MemoryBackend, WhatsAppBackend, WhatsAppClient, WhatsAppEvent};
 
 /// ZapTUI - WhatsApp Terminal User Interface
 #[derive(Parser, Debug)]
 #[command(name = "zaptui")]
 #[command(version, about = "A fast and beautiful TUI for WhatsApp", long_about = None)]
 struct Cli {
-    // No additional arguments for now, but --version and --help are automatic
+    /// Run against built-in sample chats instead of the WhatsApp service
+    #[arg(long)]
+    demo: bool,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // Parse CLI arguments (this handles --version and --help automatically)
-    let _cli = Cli::parse();
+    let cli = Cli::parse();
 
     // Initialize logging
     env_logger::init();
@@ -41,15 +44,19 @@ async fn main() -> Result<()> {
     let config = Config::load()?;
     log::info!("Configuration loaded from: {}", config.config_path.display());
 
-    // Setup terminal
-    enable_raw_mode()?;
+    // Ask the terminal about images now: once the UI reads input, replies would show up as keys
+    let image_protocol = ui::detect_protocol(&config.media);
+
+    // Setup terminal (raw mode comes with enter_tui)
+    // Terminals with the Kitty keyboard protocol tell Shift+Enter apart from Enter
+    let keyboard_enhanced = supports_keyboard_enhancement().unwrap_or(false);
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    enter_tui(&mut stdout, keyboard_enhanced)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     // Run app
-    let result = run_app(&mut terminal, config).await;
+    let result = run_app(&mut terminal, config, cli.demo, keyboard_enhanced, image_protocol).await;
 
     // Flush pending input events before cleanup to avoid escape codes leaking to terminal
     while event::poll(std::time::Duration::from_millis(0))? {
@@ -57,8 +64,7 @@ async fn main() -> Result<()> {
     }
 
     // Cleanup terminal
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), DisableMouseCapture, LeaveAlternateScreen)?;
+    leave_tui(terminal.backend_mut(), keyboard_enhanced)?;
     terminal.show_cursor()?;
 
     // Print any errors
@@ -69,16 +75,95 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-async fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, config: Config) -> Result<()> {
+/// Raw mode, the alternate screen and the input modes the UI relies on
+fn enter_tui(out: &mut impl io::Write, keyboard_enhanced: bool) -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(out, EnterAlternateScreen, EnableMouseCapture, EnableBracketedPaste)?;
+    if keyboard_enhanced {
+        execute!(out, PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES))?;
+    }
+    Ok(())
+}
+
+/// Hand the terminal back in the state we found it
+fn leave_tui(out: &mut impl io::Write, keyboard_enhanced: bool) -> io::Result<()> {
+    if keyboard_enhanced {
+        execute!(out, PopKeyboardEnhancementFlags)?;
+    }
+    execute!(out, DisableBracketedPaste, DisableMouseCapture, LeaveAlternateScreen)?;
+    disable_raw_mode()
+}
+
+/// Edit `text` in $VISUAL or $EDITOR (vi if neither is set), None if the editor failed
+fn run_editor(text: &str) -> Result<Option<String>> {
+    let mut file = tempfile::Builder::new().prefix("zaptui-").suffix(".txt").tempfile()?;
+    file.write_all(text.as_bytes())?;
+    file.flush()?;
+
+    // The variable may carry arguments, as in "code --wait"
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let status = std::process::Command::new(program)
+        .args(words)
+        .arg(file.path())
+        .status()
+        .with_context(|| format!("Cannot run editor '{}'", editor))?;
+    if !status.success() {
+        log::info!("Editor exited with {}, keeping the draft", status);
+        return Ok(None);
+    }
+
+    // Editors end the file with a newline that isn't part of the message
+    let edited = std::fs::read_to_string(file.path())?;
+    Ok(Some(edited.trim_end_matches(['\n', '\r']).to_string()))
+}
+
+async fn run_app(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    config: Config,
+    demo: bool,
+    keyboard_enhanced: bool,
+    image_protocol: ImageProtocol,
+) -> Result<()> {
     // Create channels for WhatsApp events
     let (event_tx, mut event_rx) = mpsc::channel::<WhatsAppEvent>(100);
 
-    // Connect to WhatsApp service
-    log::info!("Connecting to WhatsApp service at {}", config.whatsapp.service_url);
-    let whatsapp_client = WhatsAppClient::connect(&config.whatsapp.service_url, event_tx.clone()).await?;
+    let backend: Arc<dyn WhatsAppBackend> = if demo {
+        log::info!("Running in demo mode with in-memory backend");
+        let backend = MemoryBackend::demo(event_tx);
+        backend.start().await?;
+        Arc::new(backend)
+    } else {
+        // Connect to WhatsApp service
+        log::info!("Connecting to WhatsApp service at {}", config.whatsapp.service_url);
+        match WhatsAppClient::connect(&config.whatsapp.service_url, event_tx.clone()).await {
+            Ok(client) => Arc::new(client),
+            Err(e) => {
+                // Keep going with cached history and let the supervisor redial
+                log::warn!("{:#}. Starting offline", e);
+                Arc::new(WhatsAppClient::connect_in_background(&config.whatsapp.service_url, event_tx))
+            }
+        }
+    };
+    
+    // Open the local cache (demo data stays out of it)
+    let store = if demo {
+        None
+    } else {
+        match MessageStore::open_default() {
+            Ok(store) => Some(store),
+            Err(e) => {
+                log::warn!("Message store unavailable, running without cache: {:#}", e);
+                None
+            }
+        }
+    };
     
     // Create app state
-    let mut app = App::new(config.clone(), whatsapp_client, event_tx.clone());
+    let mut app = App::new(config.clone(), backend, store, image_protocol);
 
     // Create a periodic sync timer (every 30 seconds for current chat)
     let mut sync_interval = tokio::time::interval(tokio::time::Duration::from_secs(30));
@@ -96,6 +181,8 @@ async fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, config:
             terminal.draw(|frame| {
                 app.render(frame);
             })?;
+            app.flush_graphics(terminal.backend_mut())?;
+            app.flush_clipboard(terminal.backend_mut())?;
             needs_render = false;
         }
 
@@ -118,12 +205,11 @@ async fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, config:
                 if let Ok(Ok(Some(terminal_event))) = result {
                     // Handle Ctrl+C to quit
                     if let Event::Key(key) = &terminal_event {
-                        if key.kind == KeyEventKind::Press {
-                            if key.code == KeyCode::Char('c') 
-                                && key.modifiers.contains(event::KeyModifiers::CONTROL) {
-                                log::info!("User requested quit via Ctrl+C");
-                                break;
-                            }
+                        if key.kind == KeyEventKind::Press
+                            && key.code == KeyCode::Char('c')
+                            && key.modifiers.contains(event::KeyModifiers::CONTROL) {
+                            log::info!("User requested quit via Ctrl+C");
+                            break;
                         }
                     }
                     
@@ -131,6 +217,18 @@ async fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, config:
                     if app.handle_event(terminal_event).await? {
                         break; // App requested quit
                     }
+
+                    // Ctrl+E: the UI steps aside while $EDITOR has the draft
+                    if let Some(draft) = app.take_editor_request() {
+                        app.clear_graphics(terminal.backend_mut())?;
+                        leave_tui(terminal.backend_mut(), keyboard_enhanced)?;
+                        // Blocks until the editor exits; other tasks move to another worker meanwhile
+                        let edited = tokio::task::block_in_place(|| run_editor(&draft));
+                        enter_tui(terminal.backend_mut(), keyboard_enhanced)?;
+                        terminal.hide_cursor()?;
+                        terminal.clear()?;
+                        app.finish_editor(edited);
+                    }
                     
                     // Mark for re-render after input
                     needs_render = true;
@@ -149,6 +247,8 @@ async fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, config:
                 if let Err(e) = app.refresh_current_chat_messages().await {
                     log::warn!("Periodic sync failed: {}", e);
                 }
+                // Catch up on queued messages that missed their retry (e.g. while offline)
+                app.flush_outbox();
                 needs_render = true;
             }
 
@@ -160,5 +260,8 @@ async fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, config:
         }
     }
 
+    app.stash_draft();
+    app.clear_graphics(terminal.backend_mut())?;
+
     Ok(())
 }
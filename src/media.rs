@@ -0,0 +1,176 @@
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::config::MediaConfig;
+use crate::whatsapp::{MediaDownload, Message};
+
+/// Attachment saved to disk
+#[derive(Debug, Clone)]
+pub struct DownloadedMedia {
+    pub message_id: String,
+    pub sha256: String,
+    pub path: PathBuf,
+}
+
+/// Saves downloaded attachments under `MediaConfig.download_path`
+///
+/// Files are de-duplicated by checksum: the same photo forwarded to three
+/// chats is written once and every message points at that file.
+#[derive(Clone)]
+pub struct MediaManager {
+    download_dir: PathBuf,
+    auto_download: bool,
+    by_checksum: Arc<Mutex<HashMap<String, PathBuf>>>,
+}
+
+impl MediaManager {
+    pub fn new(config: &MediaConfig, known: &[DownloadedMedia]) -> Self {
+        let by_checksum = known.iter()
+            .map(|m| (m.sha256.clone(), m.path.clone()))
+            .collect();
+
+        // Absolute, so saved paths stay valid when zaptui starts from another directory
+        let download_dir = expand_home(&config.download_path);
+        let download_dir = std::path::absolute(&download_dir).unwrap_or(download_dir);
+
+        Self {
+            download_dir,
+            auto_download: config.auto_download,
+            by_checksum: Arc::new(Mutex::new(by_checksum)),
+        }
+    }
+
+    /// Download attachments of incoming messages without being asked
+    pub fn auto_download(&self) -> bool {
+        self.auto_download
+    }
+
+    /// Write an attachment to disk, reusing an existing file with the same content
+    pub fn save(&self, msg: &Message, download: &MediaDownload) -> Result<DownloadedMedia> {
+        let sha256 = format!("{:x}", Sha256::digest(&download.data));
+
+        let existing = self.by_checksum.lock().unwrap().get(&sha256).cloned();
+        if let Some(path) = existing.filter(|p| p.exists()) {
+            log::info!("Media for {} already saved at {:?}", msg.id, path);
+            return Ok(DownloadedMedia { message_id: msg.id.clone(), sha256, path });
+        }
+
+        std::fs::create_dir_all(&self.download_dir)
+            .with_context(|| format!("Cannot create {}", self.download_dir.display()))?;
+
+        let path = unique_path(&self.download_dir, &file_name(msg, download));
+        std::fs::write(&path, &download.data)
+            .with_context(|| format!("Cannot write {}", path.display()))?;
+        log::info!("Saved media for {} to {:?}", msg.id, path);
+
+        self.by_checksum.lock().unwrap().insert(sha256.clone(), path.clone());
+        Ok(DownloadedMedia { message_id: msg.id.clone(), sha256, path })
+    }
+}
+
+/// Open a file with the system's default application
+pub fn open_file(path: &Path) -> Result<()> {
+    open::that_detached(path).with_context(|| format!("Cannot open {}", path.display()))
+}
+
+/// Open a link in the default browser
+pub fn open_url(url: &str) -> Result<()> {
+    open::that_detached(url).with_context(|| format!("Cannot open {}", url))
+}
+
+/// Original file name when there is one, otherwise `<type>-<date>-<time>.<ext>`
+fn file_name(msg: &Message, download: &MediaDownload) -> String {
+    if let Some(name) = download.filename.as_deref().map(sanitize).filter(|n| !n.is_empty()) {
+        return name;
+    }
+
+    let kind = msg.media_type.as_deref().unwrap_or("media");
+    let time = chrono::DateTime::from_timestamp(msg.timestamp, 0)
+        .map(|dt| dt.with_timezone(&chrono::Local).format("%Y%m%d-%H%M%S").to_string())
+        .unwrap_or_default();
+    sanitize(&format!("{}-{}.{}", kind, time, extension(&download.mimetype)))
+}
+
+/// The usual extension for a MIME type, parameters like `; codecs=opus` ignored
+///
+/// mime_guess lists extensions alphabetically, so its first pick for common
+/// types is an odd one (`.jfif` for JPEG); those come from this table instead.
+fn extension(mimetype: &str) -> &'static str {
+    let essence = mimetype.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    match essence.as_str() {
+        "image/jpeg" => "jpg",
+        "image/webp" => "webp",
+        "audio/mpeg" => "mp3",
+        "audio/ogg" => "ogg",
+        "video/mp4" => "mp4",
+        "application/pdf" => "pdf",
+        other => mime_guess::get_mime_extensions_str(other)
+            .and_then(|exts| exts.first())
+            .copied()
+            .unwrap_or("bin"),
+    }
+}
+
+/// Strip path separators and characters most filesystems reject
+fn sanitize(name: &str) -> String {
+    let cleaned: String = name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    // No hidden files or `..` tricks
+    cleaned.trim().trim_start_matches('.').to_string()
+}
+
+/// `dir/name`, or `dir/stem (n).ext` if that is taken
+fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    let candidate = dir.join(name);
+    if !candidate.exists() {
+        return candidate;
+    }
+
+    let path = Path::new(name);
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let ext = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+
+    (1..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, ext)))
+        .find(|p| !p.exists())
+        .expect("unbounded range always finds a free name")
+}
+
+/// Expand a leading `~` to the home directory
+pub fn expand_home(path: &str) -> PathBuf {
+    let rest = if path == "~" { Some("") } else { path.strip_prefix("~/") };
+    match (rest, dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extensions_for_common_types() {
+        for (mimetype, ext) in [
+            ("image/jpeg", "jpg"),
+            ("image/webp", "webp"),
+            ("audio/mpeg", "mp3"),
+            ("audio/ogg; codecs=opus", "ogg"),
+            ("video/mp4", "mp4"),
+            ("application/pdf", "pdf"),
+            ("image/png", "png"),
+            ("application/x-unknown", "bin"),
+        ] {
+            assert_eq!(extension(mimetype), ext, "{}", mimetype);
+        }
+    }
+}
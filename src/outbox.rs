@@ -0,0 +1,172 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use crate::whatsapp::ServiceError;
+
+/// Transient failures are retried this many times before giving up
+pub const MAX_ATTEMPTS: u32 = 5;
+
+/// Wait before the first retry, doubled after each further failure
+const RETRY_BASE: Duration = Duration::from_secs(2);
+
+/// Delivery state of a queued outgoing message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutboxState {
+    Pending,  // Waiting for a connection or its turn in the chat queue
+    Sending,  // Request in flight (never persisted)
+    Failed,   // Gave up, needs retry or discard from the user
+}
+
+impl OutboxState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OutboxState::Pending | OutboxState::Sending => "pending",
+            OutboxState::Failed => "failed",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "failed" => OutboxState::Failed,
+            _ => OutboxState::Pending,
+        }
+    }
+}
+
+/// Outgoing message that has not been confirmed by the service yet
+#[derive(Debug, Clone)]
+pub struct OutboxEntry {
+    pub id: String,
+    pub chat_id: String,
+    pub text: String,
+    pub quoted_id: Option<String>,  // Message this one replies to
+    pub created_at: i64,
+    pub attempts: u32,
+    pub state: OutboxState,
+    pub error: Option<String>,
+    pub retry_at: Option<Instant>,  // Backoff after a transient failure (never persisted)
+}
+
+impl OutboxEntry {
+    pub fn new(chat_id: &str, text: &str, quoted_id: Option<String>) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            chat_id: chat_id.to_string(),
+            text: text.to_string(),
+            quoted_id,
+            created_at: chrono::Utc::now().timestamp(),
+            attempts: 0,
+            state: OutboxState::Pending,
+            error: None,
+            retry_at: None,
+        }
+    }
+
+    /// Record a failed attempt; requeue it with backoff unless the failure is permanent
+    ///
+    /// Returns how long until the retry is due, if there is one.
+    pub fn record_failure(&mut self, error: String, permanent: bool) -> Option<Duration> {
+        self.attempts += 1;
+        self.error = Some(error);
+        if permanent || self.attempts >= MAX_ATTEMPTS {
+            self.state = OutboxState::Failed;
+            self.retry_at = None;
+            return None;
+        }
+
+        let delay = RETRY_BASE * 2u32.pow(self.attempts - 1);
+        self.state = OutboxState::Pending;
+        self.retry_at = Some(Instant::now() + delay);
+        Some(delay)
+    }
+
+    /// Whether a pending entry has waited out its backoff
+    pub fn is_due(&self) -> bool {
+        self.retry_at.is_none_or(|at| at <= Instant::now())
+    }
+
+    /// Give a failed entry a fresh set of attempts
+    pub fn reset(&mut self) {
+        self.attempts = 0;
+        self.error = None;
+        self.state = OutboxState::Pending;
+        self.retry_at = None;
+    }
+}
+
+/// Indices of the entries to send now, oldest first
+///
+/// Chats are sent in order: only a chat's oldest pending entry goes, once its
+/// backoff is over and nothing else of the chat is in flight.
+pub fn next_to_send(entries: &[OutboxEntry]) -> Vec<usize> {
+    let mut busy_chats: HashSet<&str> = entries.iter()
+        .filter(|e| e.state == OutboxState::Sending)
+        .map(|e| e.chat_id.as_str())
+        .collect();
+
+    entries.iter()
+        .enumerate()
+        .filter(|(_, e)| e.state == OutboxState::Pending && busy_chats.insert(&e.chat_id) && e.is_due())
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Whether a send error is worth retrying later
+pub fn is_permanent(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<ServiceError>().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_until_attempts_run_out() {
+        let mut entry = OutboxEntry::new("chat", "hi", None);
+        for attempt in 0..MAX_ATTEMPTS - 1 {
+            assert_eq!(entry.record_failure("offline".to_string(), false), Some(RETRY_BASE * 2u32.pow(attempt)));
+            assert_eq!(entry.state, OutboxState::Pending);
+        }
+        assert_eq!(entry.record_failure("offline".to_string(), false), None);
+        assert_eq!(entry.state, OutboxState::Failed);
+        assert_eq!(entry.attempts, MAX_ATTEMPTS);
+
+        // Permanent errors fail right away
+        let mut entry = OutboxEntry::new("chat", "hi", None);
+        assert_eq!(entry.record_failure("not a contact".to_string(), true), None);
+        assert_eq!(entry.state, OutboxState::Failed);
+    }
+
+    #[test]
+    fn due_once_the_backoff_is_over() {
+        let mut entry = OutboxEntry::new("chat", "hi", None);
+        assert!(entry.is_due());
+        entry.record_failure("offline".to_string(), false);
+        assert!(!entry.is_due());
+        entry.retry_at = Some(Instant::now());
+        assert!(entry.is_due());
+    }
+
+    #[test]
+    fn sends_each_chat_in_order() {
+        let mut entries: Vec<OutboxEntry> = [("a", "1"), ("a", "2"), ("b", "3"), ("c", "4"), ("c", "5")]
+            .into_iter()
+            .map(|(chat, text)| OutboxEntry::new(chat, text, None))
+            .collect();
+        entries[2].record_failure("offline".to_string(), false);
+        entries[3].state = OutboxState::Failed;
+
+        // "b" waits out its backoff, and a failed message doesn't hold up "c"
+        assert_eq!(next_to_send(&entries), [0, 4]);
+
+        entries[0].state = OutboxState::Sending;
+        entries[4].state = OutboxState::Sending;
+        assert_eq!(next_to_send(&entries), Vec::<usize>::new());
+
+        // The next of "a" goes once the first is confirmed
+        entries.remove(0);
+        assert_eq!(next_to_send(&entries), [0]);
+    }
+}
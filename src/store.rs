@@ -0,0 +1,472 @@
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::media::DownloadedMedia;
+use crate::outbox::{OutboxEntry, OutboxState};
+use crate::whatsapp::{Chat, Message};
+
+/// Schema migrations, applied in order and tracked with `PRAGMA user_version`
+const MIGRATIONS: &[&str] = &[
+    // 1: chats and messages, stored as JSON so new fields need no migration
+    "CREATE TABLE chats (
+        id TEXT PRIMARY KEY,
+        timestamp INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE messages (
+        id TEXT PRIMARY KEY,
+        chat_id TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX messages_by_chat ON messages (chat_id, timestamp);",
+    // 2: outgoing messages not yet confirmed by the service
+    "CREATE TABLE outbox (
+        id TEXT PRIMARY KEY,
+        chat_id TEXT NOT NULL,
+        text TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        attempts INTEGER NOT NULL DEFAULT 0,
+        state TEXT NOT NULL,
+        error TEXT
+    );",
+    // 3: replies queued in the outbox
+    "ALTER TABLE outbox ADD COLUMN quoted_id TEXT;",
+    // 4: attachments saved to disk, by message
+    "CREATE TABLE media (
+        message_id TEXT PRIMARY KEY,
+        sha256 TEXT NOT NULL,
+        path TEXT NOT NULL
+    );",
+    // 5: profile pictures by chat; NULL data means the chat has none
+    "CREATE TABLE avatars (
+        chat_id TEXT PRIMARY KEY,
+        data BLOB,
+        fetched_at INTEGER NOT NULL
+    );",
+    // 6: unsent composer text by chat
+    "CREATE TABLE drafts (
+        chat_id TEXT PRIMARY KEY,
+        text TEXT NOT NULL
+    );",
+    // 7: search index of message text. Its rowid is the message's `key`, an
+    // INTEGER PRIMARY KEY so VACUUM can't renumber it as it can a plain rowid.
+    // Trigrams match any substring, with Unicode case folding
+    "CREATE TABLE messages_keyed (
+        key INTEGER PRIMARY KEY,
+        id TEXT NOT NULL UNIQUE,
+        chat_id TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    INSERT INTO messages_keyed (id, chat_id, timestamp, data)
+        SELECT id, chat_id, timestamp, data FROM messages;
+    DROP TABLE messages;
+    ALTER TABLE messages_keyed RENAME TO messages;
+    CREATE INDEX messages_by_chat ON messages (chat_id, timestamp);
+    CREATE VIRTUAL TABLE message_text USING fts5(body, tokenize = 'trigram');
+    INSERT INTO message_text (rowid, body)
+        SELECT key, json_extract(data, '$.body') FROM messages
+        WHERE NOT coalesce(json_extract(data, '$.deleted'), 0);",
+];
+
+/// Shorter queries have no trigram to look up and scan the index instead
+const MIN_INDEXED_QUERY: usize = 3;
+
+/// Persistent local cache of chats and messages
+///
+/// Lives under the XDG data dir (`~/.local/share/zaptui/store.db` on Linux)
+/// so the UI can show history instantly on startup and while offline.
+pub struct MessageStore {
+    conn: Connection,
+}
+
+impl MessageStore {
+    /// Open the store in the default data directory
+    pub fn open_default() -> Result<Self> {
+        let data_dir = dirs::data_dir()
+            .context("Could not find data directory")?
+            .join("zaptui");
+
+        if !data_dir.exists() {
+            std::fs::create_dir_all(&data_dir)?;
+            log::info!("Created data directory: {:?}", data_dir);
+        }
+
+        Self::open(&data_dir.join("store.db"))
+    }
+
+    /// Open (or create) a store at the given path
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open message store at {}", path.display()))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+
+        let mut store = Self { conn };
+        store.migrate()?;
+        log::info!("Opened message store at {:?}", path);
+        Ok(store)
+    }
+
+    fn migrate(&mut self) -> Result<()> {
+        let version: usize = self.conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+        let tx = self.conn.transaction()?;
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            log::info!("Applying message store migration {}", i + 1);
+            tx.execute_batch(migration)
+                .with_context(|| format!("Message store migration {} failed", i + 1))?;
+        }
+        tx.pragma_update(None, "user_version", MIGRATIONS.len())?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// All cached chats, most recent first
+    pub fn load_chats(&self) -> Result<Vec<Chat>> {
+        let mut stmt = self.conn.prepare("SELECT data FROM chats ORDER BY timestamp DESC")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+
+        let mut chats = Vec::new();
+        for data in rows {
+            match serde_json::from_str(&data?) {
+                Ok(chat) => chats.push(chat),
+                Err(e) => log::warn!("Skipping unreadable cached chat: {}", e),
+            }
+        }
+        Ok(chats)
+    }
+
+    /// Replace the cached chat list with a fresh one from the service
+    pub fn replace_chats(&mut self, chats: &[Chat]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM chats", [])?;
+        for chat in chats {
+            upsert_chat(&tx, chat)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Insert or update a single chat
+    pub fn save_chat(&self, chat: &Chat) -> Result<()> {
+        upsert_chat(&self.conn, chat)
+    }
+
+    /// Latest `limit` cached messages for a chat, oldest first
+    pub fn load_messages(&self, chat_id: &str, limit: usize) -> Result<Vec<Message>> {
+        let mut messages = self.query_messages(
+            "SELECT data FROM messages WHERE chat_id = ?1 ORDER BY timestamp DESC LIMIT ?2",
+            params![chat_id, limit as i64],
+        )?;
+        messages.reverse();
+        Ok(messages)
+    }
+
+    /// Up to `before` of a chat's messages ahead of `timestamp` and `after` from it on, oldest first
+    pub fn load_messages_around(&self, chat_id: &str, timestamp: i64, before: usize, after: usize) -> Result<Vec<Message>> {
+        let mut messages = self.query_messages(
+            "SELECT data FROM messages WHERE chat_id = ?1 AND timestamp < ?2 ORDER BY timestamp DESC LIMIT ?3",
+            params![chat_id, timestamp, before as i64],
+        )?;
+        messages.reverse();
+        messages.extend(self.query_messages(
+            "SELECT data FROM messages WHERE chat_id = ?1 AND timestamp >= ?2 ORDER BY timestamp LIMIT ?3",
+            params![chat_id, timestamp, after as i64],
+        )?);
+        Ok(messages)
+    }
+
+    /// Messages in any chat whose text contains `query`, newest first
+    ///
+    /// Case is ignored for any letter, except in queries of one or two
+    /// characters: those fall back to LIKE, which only folds ASCII.
+    pub fn search_messages(&self, query: &str, limit: usize) -> Result<Vec<Message>> {
+        if query.chars().count() >= MIN_INDEXED_QUERY {
+            // One quoted phrase, so operators typed by the user match literally
+            let phrase = format!("\"{}\"", query.replace('"', "\"\""));
+            return self.query_messages(
+                "SELECT m.data FROM message_text JOIN messages m ON m.key = message_text.rowid
+                 WHERE message_text MATCH ?1
+                 ORDER BY m.timestamp DESC LIMIT ?2",
+                params![phrase, limit as i64],
+            );
+        }
+
+        // Wildcards typed by the user match literally
+        let pattern = format!("%{}%", query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+        self.query_messages(
+            "SELECT m.data FROM message_text JOIN messages m ON m.key = message_text.rowid
+             WHERE message_text.body LIKE ?1 ESCAPE '\\'
+             ORDER BY m.timestamp DESC LIMIT ?2",
+            params![pattern, limit as i64],
+        )
+    }
+
+    /// Run a query selecting message `data`, skipping rows that no longer parse
+    fn query_messages(&self, sql: &str, params: impl rusqlite::Params) -> Result<Vec<Message>> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let rows = stmt.query_map(params, |row| row.get::<_, String>(0))?;
+
+        let mut messages = Vec::new();
+        for data in rows {
+            match serde_json::from_str(&data?) {
+                Ok(msg) => messages.push(msg),
+                Err(e) => log::warn!("Skipping unreadable cached message: {}", e),
+            }
+        }
+        Ok(messages)
+    }
+
+    /// Insert or update messages, keeping the search index in step
+    pub fn save_messages(&mut self, messages: &[Message]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut save = tx.prepare(
+                "INSERT INTO messages (id, chat_id, timestamp, data) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(id) DO UPDATE SET chat_id = excluded.chat_id,
+                     timestamp = excluded.timestamp, data = excluded.data
+                 RETURNING key",
+            )?;
+            let mut unindex = tx.prepare("DELETE FROM message_text WHERE rowid = ?1")?;
+            let mut index = tx.prepare("INSERT INTO message_text (rowid, body) VALUES (?1, ?2)")?;
+            for msg in messages {
+                let key: i64 = save.query_row(
+                    params![msg.id, msg.chat_id, msg.timestamp, serde_json::to_string(msg)?],
+                    |row| row.get(0),
+                )?;
+                // Edited text replaces the old, revoked messages can't be found
+                unindex.execute(params![key])?;
+                if !msg.deleted {
+                    index.execute(params![key, msg.body])?;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Forget a message deleted for us
+    pub fn remove_message(&self, id: &str) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM message_text WHERE rowid = (SELECT key FROM messages WHERE id = ?1)",
+            params![id],
+        )?;
+        self.conn.execute("DELETE FROM messages WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// Every attachment saved so far
+    pub fn load_media(&self) -> Result<Vec<DownloadedMedia>> {
+        let mut stmt = self.conn.prepare("SELECT message_id, sha256, path FROM media")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(DownloadedMedia {
+                message_id: row.get(0)?,
+                sha256: row.get(1)?,
+                path: std::path::PathBuf::from(row.get::<_, String>(2)?),
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Remember where a message's attachment was saved
+    pub fn save_media(&self, media: &DownloadedMedia) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO media (message_id, sha256, path) VALUES (?1, ?2, ?3)
+             ON CONFLICT(message_id) DO UPDATE SET sha256 = excluded.sha256, path = excluded.path",
+            params![media.message_id, media.sha256, media.path.to_string_lossy()],
+        )?;
+        Ok(())
+    }
+
+    /// Cached profile picture of a chat and when it was fetched, None if never fetched
+    pub fn load_avatar(&self, chat_id: &str) -> Result<Option<(Option<Vec<u8>>, i64)>> {
+        let mut stmt = self.conn.prepare("SELECT data, fetched_at FROM avatars WHERE chat_id = ?1")?;
+        let mut rows = stmt.query_map(params![chat_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.next().transpose()?)
+    }
+
+    /// Cache a chat's profile picture, or the fact that it has none
+    pub fn save_avatar(&self, chat_id: &str, data: Option<&[u8]>) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO avatars (chat_id, data, fetched_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(chat_id) DO UPDATE SET data = excluded.data, fetched_at = excluded.fetched_at",
+            params![chat_id, data, chrono::Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    /// Unsent composer text of every chat that has some
+    pub fn load_drafts(&self) -> Result<HashMap<String, String>> {
+        let mut stmt = self.conn.prepare("SELECT chat_id, text FROM drafts")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Remember a chat's unsent text, or forget it if empty
+    pub fn save_draft(&self, chat_id: &str, text: &str) -> Result<()> {
+        if text.is_empty() {
+            self.conn.execute("DELETE FROM drafts WHERE chat_id = ?1", params![chat_id])?;
+        } else {
+            self.conn.execute(
+                "INSERT INTO drafts (chat_id, text) VALUES (?1, ?2)
+                 ON CONFLICT(chat_id) DO UPDATE SET text = excluded.text",
+                params![chat_id, text],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// All queued outgoing messages, oldest first
+    pub fn load_outbox(&self) -> Result<Vec<OutboxEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, chat_id, text, created_at, attempts, state, error, quoted_id
+             FROM outbox ORDER BY created_at",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(OutboxEntry {
+                id: row.get(0)?,
+                chat_id: row.get(1)?,
+                text: row.get(2)?,
+                created_at: row.get(3)?,
+                attempts: row.get(4)?,
+                state: OutboxState::parse(&row.get::<_, String>(5)?),
+                error: row.get(6)?,
+                quoted_id: row.get(7)?,
+                retry_at: None,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Insert or update a queued outgoing message
+    pub fn save_outbox_entry(&self, entry: &OutboxEntry) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO outbox (id, chat_id, text, created_at, attempts, state, error, quoted_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT(id) DO UPDATE SET attempts = excluded.attempts,
+                 state = excluded.state, error = excluded.error",
+            params![
+                entry.id,
+                entry.chat_id,
+                entry.text,
+                entry.created_at,
+                entry.attempts,
+                entry.state.as_str(),
+                entry.error,
+                entry.quoted_id,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Drop a queued outgoing message once sent or discarded
+    pub fn remove_outbox_entry(&self, id: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM outbox WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+}
+
+fn upsert_chat(conn: &Connection, chat: &Chat) -> Result<()> {
+    conn.execute(
+        "INSERT INTO chats (id, timestamp, data) VALUES (?1, ?2, ?3)
+         ON CONFLICT(id) DO UPDATE SET timestamp = excluded.timestamp, data = excluded.data",
+        params![chat.id, chat.timestamp, serde_json::to_string(chat)?],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: &str, body: &str, timestamp: i64) -> Message {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "chat_id": "chat",
+            "body": body,
+            "timestamp": timestamp,
+            "from_me": false,
+        }))
+        .unwrap()
+    }
+
+    fn found(store: &MessageStore, query: &str) -> Vec<String> {
+        store.search_messages(query, 10).unwrap().into_iter().map(|m| m.id).collect()
+    }
+
+    #[test]
+    fn search_follows_saved_messages() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = MessageStore::open(&dir.path().join("store.db")).unwrap();
+        store.save_messages(&[
+            message("a", "Meet at the ÉCOLE at 9", 1),
+            message("b", "Ok, see you at the école", 2),
+            message("c", "50% off, use code_x", 3),
+        ]).unwrap();
+
+        // Any case of any letter, newest first
+        assert_eq!(found(&store, "école"), ["b", "a"]);
+        // Short queries and LIKE wildcards typed as text
+        assert_eq!(found(&store, "ok"), ["b"]);
+        assert_eq!(found(&store, "%"), ["c"]);
+        assert_eq!(found(&store, "e_x"), ["c"]);
+        assert_eq!(found(&store, "\"code"), Vec::<String>::new());
+
+        // Edits replace the indexed text, revoked and removed messages drop out
+        let mut edited = message("a", "Meet at the library", 1);
+        edited.edited = true;
+        let mut revoked = message("b", "", 2);
+        revoked.deleted = true;
+        store.save_messages(&[edited, revoked]).unwrap();
+        store.remove_message("c").unwrap();
+        assert_eq!(found(&store, "école"), Vec::<String>::new());
+        assert_eq!(found(&store, "library"), ["a"]);
+        assert_eq!(found(&store, "%"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn search_survives_vacuum_and_upgrades() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.db");
+
+        // A store from before the search index
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(&MIGRATIONS[..6].join("\n")).unwrap();
+            conn.pragma_update(None, "user_version", 6).unwrap();
+            let old = message("old", "Cached before the upgrade", 1);
+            conn.execute(
+                "INSERT INTO messages (id, chat_id, timestamp, data) VALUES (?1, ?2, ?3, ?4)",
+                params![old.id, old.chat_id, old.timestamp, serde_json::to_string(&old).unwrap()],
+            ).unwrap();
+        }
+
+        let mut store = MessageStore::open(&path).unwrap();
+        assert_eq!(found(&store, "upgrade"), ["old"]);
+
+        // Gaps in the keys, then a VACUUM, which may renumber plain rowids
+        store.save_messages(&[
+            message("a", "first one", 2),
+            message("b", "second one", 3),
+            message("c", "third one", 4),
+        ]).unwrap();
+        store.remove_message("a").unwrap();
+        store.conn.execute_batch("VACUUM").unwrap();
+        assert_eq!(found(&store, "second"), ["b"]);
+        assert_eq!(found(&store, "one"), ["c", "b"]);
+    }
+
+    #[test]
+    fn context_around_a_message_is_bounded() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = MessageStore::open(&dir.path().join("store.db")).unwrap();
+        let messages: Vec<Message> = (0..10).map(|i| message(&i.to_string(), "hi", i)).collect();
+        store.save_messages(&messages).unwrap();
+
+        let around: Vec<String> = store.load_messages_around("chat", 5, 2, 3).unwrap().into_iter().map(|m| m.id).collect();
+        assert_eq!(around, ["3", "4", "5", "6", "7"]);
+    }
+}
@@ -1,20 +1,68 @@
 use anyhow::Result;
-use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, MouseEvent, MouseEventKind, MouseButton};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseEvent, MouseEventKind, MouseButton};
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Modifier, Style},
+    style::{Color, Modifier, Style},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, BorderType, List, ListItem, ListState, Paragraph, Wrap},
+    widgets::{Block, Borders, BorderType, List, ListItem, ListState, Paragraph},
     Frame,
 };
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc;
 
 use crate::config::Config;
-use crate::whatsapp::{Chat, Message, WhatsAppClient, WhatsAppEvent};
+use crate::media::{self, MediaManager};
+use crate::outbox::{self, OutboxEntry, OutboxState};
+use crate::store::MessageStore;
+use crate::whatsapp::{AckStatus, Chat, MediaUpload, Message, Reaction, WhatsAppBackend, WhatsAppEvent};
+use super::clock::Clock;
+use super::format;
+use super::fuzzy;
+use super::graphics::{self, Graphics, ImageKey, Protocol as ImageProtocol};
 use super::theme::Theme;
+use super::wrap;
+use super::components::composer::Composer;
+use super::components::forward_picker::{ForwardPicker, PickerInput};
+use super::components::message_popup::{self, MenuInput, MessageAction, MessageDetails, MessageMenu, MessagePopup};
 use super::components::qr_view::QRView;
 
+/// Emojis offered by the reaction picker, same as WhatsApp's quick reactions
+const REACTION_EMOJIS: [&str; 6] = ["👍", "❤️", "😂", "😮", "😢", "🙏"];
+
+/// Image previews line up with the quote and reaction lines
+const PREVIEW_INDENT: u16 = 6;
+
+/// Most results a message search shows
+const SEARCH_LIMIT: usize = 200;
+
+/// Pause in typing before the local search runs
+const SEARCH_DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// Older messages loaded above a search result, so it's seen in context
+const SEARCH_CONTEXT: usize = 20;
+
+/// Newer messages loaded below it, usually enough to reach the chat's loaded history
+const SEARCH_FOLLOWING: usize = 500;
+
+/// Characters shown before the match in a search result
+const SNIPPET_LEAD: usize = 24;
+
+/// The input pane grows with its text up to this many rows, then scrolls
+const MAX_INPUT_ROWS: u16 = 8;
+
+/// Cached profile pictures older than this are fetched again
+const AVATAR_MAX_AGE_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Initials badge backgrounds, picked per chat so a chat keeps its color
+const BADGE_COLORS: [Color; 12] = [
+    Color::Red, Color::Green, Color::Yellow, Color::Blue, Color::Magenta, Color::Cyan,
+    Color::LightRed, Color::LightGreen, Color::LightYellow, Color::LightBlue, Color::LightMagenta, Color::LightCyan,
+];
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum AppState {
     Authenticating,
@@ -35,60 +83,241 @@ enum ChatListView {
     Archived,  // Show archived chats only
 }
 
+/// Something to do with the selected message, listed in its action menu
+/// A message's lines broken into rows, reused while the lines and width stay the same
+struct WrappedMessage {
+    width: u16,
+    lines: Vec<Line<'static>>,
+    line_rows: Vec<usize>,  // Rows taken by each line
+    rows: Vec<Line<'static>>,
+}
+
+impl WrappedMessage {
+    fn new(lines: Vec<Line<'static>>, width: u16) -> Self {
+        let wrapped: Vec<Vec<Line<'static>>> = lines.iter().map(|line| wrap::wrap_line(line, width)).collect();
+        Self {
+            width,
+            line_rows: wrapped.iter().map(Vec::len).collect(),
+            rows: wrapped.into_iter().flatten().collect(),
+            lines,
+        }
+    }
+}
+
+/// A stretch of the message view
+enum ViewBlock {
+    Rows(Vec<Line<'static>>),  // Day separators and queued messages, wrapped every frame
+    Message {
+        id: String,
+        height: usize,  // Rows, with the preview's
+        preview: Option<(usize, u16, u16)>,  // Row under the text, cols, rows
+    },
+}
+
+impl ViewBlock {
+    fn height(&self) -> usize {
+        match self {
+            ViewBlock::Rows(rows) => rows.len(),
+            ViewBlock::Message { height, .. } => *height,
+        }
+    }
+}
+
+/// Message search across chats: what's typed, what it found and the cursor
+struct Search {
+    query: String,
+    results: Vec<Message>,  // Newest first
+    list_state: ListState,
+    server_status: Option<String>,  // Progress or outcome of the last server search
+}
+
+/// Chat list filter typed after `/`, and the list state Esc goes back to
+#[derive(Debug, Clone)]
+struct ChatFilter {
+    query: String,
+    view: ChatListView,
+    selected: Option<String>,  // Chat under the cursor, None on "Archived Messages"
+    scroll: usize,
+}
+
 pub struct App {
     theme: Theme,
-    client: WhatsAppClient,
+    client: Arc<dyn WhatsAppBackend>,
     event_tx: mpsc::Sender<WhatsAppEvent>,
     state: AppState,
+    store: Option<MessageStore>,  // Persistent cache, None if it failed to open
+    media: MediaManager,
+    graphics: Graphics,  // Inline image previews and avatars
+    clock: Clock,  // Message times and day separators
     
     // Data
     chats: Vec<Chat>,
     current_chat_id: Option<String>,
     messages: HashMap<String, Vec<Message>>,
+    outbox: Vec<OutboxEntry>,  // Outgoing messages not yet confirmed, oldest first
+    downloaded: HashMap<String, PathBuf>,  // message_id -> saved attachment
+    downloads: HashMap<String, (u64, u64)>,  // In-progress downloads: message_id -> (received, total)
+    open_after_download: HashSet<String>,  // Downloads to open once saved
     
     // UI State
     focused: FocusedWidget,
     chat_list_view: ChatListView,
     chat_list_state: ListState,
     chat_list_scroll: usize,  // Scroll offset for chat list
+    chat_filter: Option<ChatFilter>,  // Filter prompt open in the chat list
+    search: Option<Search>,  // Message search shown in place of the message view
     chat_list_area: Rect,  // Store chat list area for mouse click detection
+    show_avatars: bool,  // Avatar or initials badge before each chat name
     message_view_area: Rect,  // Store message view area for mouse detection
     input_area: Rect,  // Store input area for mouse detection
     message_scroll: u16,  // Scroll offset for message view (0 = bottom/newest)
-    input_buffer: String,
+    message_max_scroll: u16,  // Wrapped content height minus view height, from last render
+    wrapped_messages: HashMap<String, WrappedMessage>,  // message_id -> rows in the message view, from last render
+    selected_message_id: Option<String>,  // Message cursor in the message view
+    follow_selection: bool,  // Scroll the selected message into view on next render
+    reaction_picker: Option<usize>,  // Highlighted emoji while the reaction picker is open
+    delete_prompt: bool,  // Asking whether to delete the selected message for us or everyone
+    message_menu: Option<MessageMenu>,  // Action or link menu of the selected message
+    message_details: bool,  // Showing the selected message's details
+    marked_messages: HashSet<String>,  // Messages picked with Space to forward together
+    forward_picker: Option<ForwardPicker>,  // Choosing the chats to forward to
+    clipboard: Option<String>,  // Copied text, handed to the terminal on the next flush
+    composer: Composer,  // Message input
+    drafts: HashMap<String, String>,  // chat_id -> unsent text of chats other than the open one
+    editor_requested: bool,  // Ctrl+E: main hands the draft to $EDITOR after this event
+    reply_to: Option<Message>,  // Message the composer is replying to
+    editing: Option<Message>,  // Own message whose text the composer replaces
+    attachment: Option<MediaUpload>,  // File sent with the next message, the text becomes its caption
     loading_more_messages: HashMap<String, bool>,  // Track if loading older messages for a chat
     chats_needing_sync: std::collections::HashSet<String>,  // Track chats with new updates that need syncing
     
     // Authentication
     qr_code: Option<String>,
+    theme_error: Option<String>,  // Shown in the status bar until the next key press
     status_message: String,
 }
 
 impl App {
-    pub fn new(_config: Config, client: WhatsAppClient, event_tx: mpsc::Sender<WhatsAppEvent>) -> Self {
-        let theme = Theme::terminal();  // Always use terminal theme
+    pub fn new(
+        config: Config,
+        client: Arc<dyn WhatsAppBackend>,
+        store: Option<MessageStore>,
+        image_protocol: ImageProtocol,
+    ) -> Self {
+        // A broken theme shouldn't keep anyone out of their chats
+        let (theme, theme_error) = match Theme::load(&config.ui.theme, &config.themes_dir()) {
+            Ok(theme) => (theme, None),
+            Err(e) => {
+                log::error!("Failed to load theme: {:#}", e);
+                (Theme::terminal(), Some(format!("⚠️  {:#}. Using the terminal theme (any key to dismiss)", e)))
+            }
+        };
+        let event_tx = client.event_sender();
+
+        // Previously seen chats show up right away from the local cache
+        let chats = match store.as_ref().map(|s| s.load_chats()) {
+            Some(Ok(chats)) => chats,
+            Some(Err(e)) => {
+                log::warn!("Failed to load cached chats: {}", e);
+                Vec::new()
+            }
+            None => Vec::new(),
+        };
+        log::info!("Loaded {} cached chats", chats.len());
+
+        // Messages queued in a previous session are retried once we're ready
+        let outbox = match store.as_ref().map(|s| s.load_outbox()) {
+            Some(Ok(outbox)) => outbox,
+            Some(Err(e)) => {
+                log::warn!("Failed to load outbox: {}", e);
+                Vec::new()
+            }
+            None => Vec::new(),
+        };
+
+        // Unsent text from the last session
+        let drafts = match store.as_ref().map(|s| s.load_drafts()) {
+            Some(Ok(drafts)) => drafts,
+            Some(Err(e)) => {
+                log::warn!("Failed to load drafts: {}", e);
+                HashMap::new()
+            }
+            None => HashMap::new(),
+        };
+
+        // Attachments saved in earlier sessions
+        let known_media = match store.as_ref().map(|s| s.load_media()) {
+            Some(Ok(media)) => media,
+            Some(Err(e)) => {
+                log::warn!("Failed to load saved media: {}", e);
+                Vec::new()
+            }
+            None => Vec::new(),
+        };
+        let media = MediaManager::new(&config.media, &known_media);
+        let graphics = Graphics::new(image_protocol);
+        let clock = Clock::new(&config.ui);
+        let downloaded = known_media.into_iter()
+            .map(|m| (m.message_id, m.path))
+            .collect();
+
+        let mut chat_list_state = ListState::default();
+        let status_message = if chats.is_empty() {
+            "Connecting to WhatsApp...".to_string()
+        } else {
+            chat_list_state.select(Some(0));
+            format!("Connecting to WhatsApp... (showing {} cached chats)", chats.len())
+        };
         
         Self {
             theme,
             client,
             event_tx,
             state: AppState::Authenticating,
-            chats: Vec::new(),
+            store,
+            media,
+            graphics,
+            clock,
+            chats,
             current_chat_id: None,
             messages: HashMap::new(),
+            outbox,
+            downloaded,
+            downloads: HashMap::new(),
+            open_after_download: HashSet::new(),
             focused: FocusedWidget::ChatList,
             chat_list_view: ChatListView::Normal,
-            chat_list_state: ListState::default(),
+            chat_list_state,
             chat_list_scroll: 0,
+            chat_filter: None,
+            search: None,
             chat_list_area: Rect::default(),
+            show_avatars: config.ui.show_avatars,
             message_view_area: Rect::default(),
             input_area: Rect::default(),
             message_scroll: 0,
-            input_buffer: String::new(),
+            message_max_scroll: 0,
+            wrapped_messages: HashMap::new(),
+            selected_message_id: None,
+            follow_selection: false,
+            reaction_picker: None,
+            delete_prompt: false,
+            message_menu: None,
+            message_details: false,
+            marked_messages: HashSet::new(),
+            forward_picker: None,
+            clipboard: None,
+            composer: Composer::new(),
+            drafts,
+            editor_requested: false,
+            reply_to: None,
+            editing: None,
+            attachment: None,
             loading_more_messages: HashMap::new(),
             chats_needing_sync: std::collections::HashSet::new(),
             qr_code: None,
-            status_message: "Connecting to WhatsApp...".to_string(),
+            theme_error,
+            status_message,
         }
     }
     
@@ -114,6 +343,9 @@ impl App {
                 self.qr_code = None;
                 self.status_message = "Syncing chats... This may take a few minutes".to_string();
 
+                // Send anything queued while we were offline
+                self.flush_outbox();
+
                 // Load chats in background - UI is already usable
                 let client = self.client.clone();
                 let event_tx = self.event_tx.clone();
@@ -136,9 +368,20 @@ impl App {
 
             WhatsAppEvent::ChatsLoaded(mut chats) => {
                  // Sort chats by timestamp (most recent first)
-                 chats.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+                 chats.sort_by_key(|c| std::cmp::Reverse(c.timestamp));
+
+                 if let Some(store) = &mut self.store {
+                     if let Err(e) = store.replace_chats(&chats) {
+                         log::warn!("Failed to cache chats: {}", e);
+                     }
+                 }
 
+                 // Reconcile with cached chats, keeping the same chat selected
+                 let selected_chat_id = self.selected_chat_id();
                  self.chats = chats;
+                 if let Some(chat_id) = selected_chat_id {
+                     self.select_chat_by_id(&chat_id);
+                 }
                  if !self.chats.is_empty() && self.chat_list_state.selected().is_none() {
                      self.chat_list_state.select(Some(0));
                  }
@@ -151,9 +394,15 @@ impl App {
             WhatsAppEvent::MessageReceived(msg) => {
             log::debug!("Received message in chat {}", msg.chat_id);
 
+            self.persist_messages(std::slice::from_ref(&msg));
+
+            if msg.has_media && !msg.from_me && self.media.auto_download() {
+                self.start_download(&msg, false);
+            }
+
             // Add/update message with deduplication
             let chat_messages = self.messages.entry(msg.chat_id.clone())
-                .or_insert_with(Vec::new);
+                .or_default();
 
             // Check if message already exists (prevents duplicates from event + sync)
             if !chat_messages.iter().any(|m| m.id == msg.id) {
@@ -173,86 +422,111 @@ impl App {
                     chat.unread_count += 1;
                 }
             }
+            self.persist_chat(&msg.chat_id);
                 // Get the currently selected chat ID (if any) before re-sorting
-                // Need to account for "Archived Messages" offset (index 0) and view filtering
-                let selected_chat_id = if let Some(visual_index) = self.chat_list_state.selected() {
-                    if visual_index == 0 {
-                        // "Archived Messages" is selected, keep it selected
-                        None
-                    } else {
-                        // Get the filtered chats for current view
-                        let filtered_chats: Vec<&Chat> = match self.chat_list_view {
-                            ChatListView::Normal => self.chats.iter().filter(|c| !c.archived).collect(),
-                            ChatListView::Archived => self.chats.iter().filter(|c| c.archived).collect(),
-                        };
-
-                        // Visual index 1+ maps to filtered_chats index 0+
-                        let filtered_index = visual_index.saturating_sub(1);
-                        filtered_chats.get(filtered_index).map(|c| c.id.clone())
-                    }
-                } else {
-                    None
-                };
+                // ("Archived Messages" at index 0 simply stays selected)
+                let selected_chat_id = self.selected_chat_id();
 
                 // Re-sort chats to bring the updated chat to the top
-                self.chats.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+                self.chats.sort_by_key(|c| std::cmp::Reverse(c.timestamp));
 
                 // Restore selection to the same chat (by ID)
                 if let Some(chat_id) = selected_chat_id {
-                    // Get filtered chats after re-sorting
-                    let filtered_chats: Vec<&Chat> = match self.chat_list_view {
-                        ChatListView::Normal => self.chats.iter().filter(|c| !c.archived).collect(),
-                        ChatListView::Archived => self.chats.iter().filter(|c| c.archived).collect(),
-                    };
-
-                    // Find the chat's new position in the filtered list
-                    if let Some(filtered_index) = filtered_chats.iter().position(|c| c.id == chat_id) {
-                        // Convert to visual index (add 1 for "Archived Messages" offset)
-                        let new_visual_index = filtered_index + 1;
-                        self.chat_list_state.select(Some(new_visual_index));
-                    }
-                } else if self.chat_list_state.selected() == Some(0) {
-                    // Keep "Archived Messages" selected
-                    self.chat_list_state.select(Some(0));
+                    self.select_chat_by_id(&chat_id);
                 }
             }
             
             WhatsAppEvent::ChatUpdated(updated_chat) => {
+                let chat_id = updated_chat.id.clone();
                 if let Some(chat) = self.chats.iter_mut().find(|c| c.id == updated_chat.id) {
                     *chat = updated_chat;
                 } else {
                     self.chats.push(updated_chat);
                 }
+                self.persist_chat(&chat_id);
             }
 
-            WhatsAppEvent::MessagesLoaded(chat_id, new_messages) => {
-                log::debug!("Messages loaded for chat {}: {} messages", chat_id, new_messages.len());
-                
-                // Merge with existing messages (for pagination)
-                if let Some(existing) = self.messages.get_mut(&chat_id) {
-                    let old_count = existing.len();
-                    log::info!("Merging {} new messages with {} existing messages", new_messages.len(), old_count);
-                    
-                    // Prepend new messages (older ones) and deduplicate
-                    let mut all_messages = new_messages.clone();
-                    all_messages.extend(existing.clone());
-                    
-                    // Deduplicate by message ID, keeping first occurrence
-                    let mut seen = std::collections::HashSet::new();
-                    all_messages.retain(|msg| seen.insert(msg.id.clone()));
-                    
-                    // Sort by timestamp (oldest first)
-                    all_messages.sort_by_key(|m| m.timestamp);
-                    
-                    *existing = all_messages;
-                    
-                    let new_count = existing.len() - old_count;
-                    log::info!("After merge: {} total messages ({} new)", existing.len(), new_count);
+            WhatsAppEvent::MessageAck(update) => {
+                log::debug!("Ack {:?} for message {}", update.ack, update.id);
+
+                let updated = self.find_message_mut(&update.chat_id, &update.id)
+                    .map(|msg| {
+                        // Acks only move forward; late events must not downgrade a read receipt
+                        if update.ack == AckStatus::Error || update.ack > msg.ack {
+                            msg.ack = update.ack;
+                        }
+                        msg.clone()
+                    });
+
+                if let Some(msg) = updated {
+                    self.persist_messages(std::slice::from_ref(&msg));
+                }
+            }
+
+            WhatsAppEvent::Reaction(update) => {
+                log::debug!("Reaction {:?} on message {}", update.emoji, update.message_id);
+
+                let updated = self.find_message_mut(&update.chat_id, &update.message_id)
+                    .map(|msg| {
+                        msg.apply_reaction(&update);
+                        msg.clone()
+                    });
+
+                if let Some(msg) = updated {
+                    self.persist_messages(std::slice::from_ref(&msg));
+                }
+            }
+
+            WhatsAppEvent::MessageEdited(edit) => {
+                log::debug!("Message {} edited", edit.id);
+
+                let updated = self.find_message_mut(&edit.chat_id, &edit.id)
+                    .map(|msg| {
+                        msg.body = edit.body.clone();
+                        msg.edited = true;
+                        msg.clone()
+                    });
+
+                if let Some(msg) = updated {
+                    self.persist_messages(std::slice::from_ref(&msg));
+                }
+            }
+
+            WhatsAppEvent::MessageRevoked(revoke) => {
+                log::debug!("Message {} deleted (for everyone: {})", revoke.id, revoke.for_everyone);
+
+                if revoke.for_everyone {
+                    // Keep a placeholder where the message was
+                    let updated = self.find_message_mut(&revoke.chat_id, &revoke.id)
+                        .map(|msg| {
+                            msg.body.clear();
+                            msg.has_media = false;
+                            msg.deleted = true;
+                            msg.clone()
+                        });
+
+                    if let Some(msg) = updated {
+                        self.persist_messages(std::slice::from_ref(&msg));
+                    }
                 } else {
-                    // Initial load
-                    log::info!("Initial load of {} messages for chat {}", new_messages.len(), chat_id);
-                    self.messages.insert(chat_id.clone(), new_messages.clone());
+                    for msgs in self.messages.values_mut() {
+                        msgs.retain(|m| m.id != revoke.id);
+                    }
+                    if self.selected_message_id.as_ref() == Some(&revoke.id) {
+                        self.selected_message_id = None;
+                    }
+                    if let Some(store) = &self.store {
+                        if let Err(e) = store.remove_message(&revoke.id) {
+                            log::warn!("Failed to remove cached message: {}", e);
+                        }
+                    }
                 }
+            }
+
+            WhatsAppEvent::MessagesLoaded(chat_id, new_messages) => {
+                log::debug!("Messages loaded for chat {}: {} messages", chat_id, new_messages.len());
+                self.persist_messages(&new_messages);
+                self.merge_messages(&chat_id, new_messages);
                 
                 // Clear loading flag
                 self.loading_more_messages.insert(chat_id.clone(), false);
@@ -270,6 +544,163 @@ impl App {
                 self.status_message = "Disconnected. Reconnecting...".to_string();
             }
 
+            WhatsAppEvent::Reconnecting(attempt) => {
+                self.state = AppState::Disconnected;
+                self.status_message = format!("Disconnected. Reconnecting (attempt {})...", attempt);
+            }
+
+            WhatsAppEvent::Reconnected => {
+                log::info!("Reconnected to WhatsApp service");
+                // Service re-announces ready/authenticated (or a fresh QR) on the new connection
+                self.state = AppState::Authenticating;
+                self.status_message = "Reconnected. Waiting for WhatsApp...".to_string();
+            }
+
+            WhatsAppEvent::OutboxSent(id) => {
+                log::info!("Message sent successfully");
+                self.status_message = "Message sent".to_string();
+
+                if let Some(pos) = self.outbox.iter().position(|e| e.id == id) {
+                    let entry = self.outbox.remove(pos);
+                    self.remove_outbox_entry(&entry.id);
+
+                    // Refresh messages to show the sent message
+                    if self.current_chat_id.as_ref() == Some(&entry.chat_id) {
+                        if let Err(e) = self.refresh_current_chat_messages().await {
+                            log::warn!("Failed to refresh after send: {}", e);
+                        }
+                    }
+                }
+
+                // Next message in that chat's queue
+                self.flush_outbox();
+            }
+
+            WhatsAppEvent::OutboxFailed(id, error, permanent) => {
+                log::error!("Failed to send message: {}", error);
+                if let Some(entry) = self.outbox.iter_mut().find(|e| e.id == id) {
+                    let retry_in = entry.record_failure(error.clone(), permanent);
+                    self.status_message = match retry_in {
+                        None => format!("Failed to send: {} (Ctrl+R: retry, Ctrl+X: discard)", error),
+                        Some(delay) => format!("Send failed, retrying in {}s: {}", delay.as_secs(), error),
+                    };
+                    let entry = entry.clone();
+                    self.save_outbox_entry(&entry);
+
+                    if let Some(delay) = retry_in {
+                        let event_tx = self.event_tx.clone();
+                        tokio::spawn(async move {
+                            tokio::time::sleep(delay).await;
+                            let _ = event_tx.send(WhatsAppEvent::OutboxRetryDue).await;
+                        });
+                    }
+                }
+            }
+
+            WhatsAppEvent::OutboxRetryDue => {
+                self.flush_outbox();
+            }
+
+            WhatsAppEvent::UploadProgress(filename, sent, total) => {
+                self.status_message = if sent < total {
+                    format!(
+                        "Uploading {}: {}% ({} / {})",
+                        filename,
+                        sent * 100 / total.max(1),
+                        format_size(sent),
+                        format_size(total),
+                    )
+                } else {
+                    format!("Sending {}...", filename)
+                };
+            }
+
+            WhatsAppEvent::MediaSent(filename) => {
+                log::info!("Sent {}", filename);
+                self.status_message = format!("Sent {}", filename);
+            }
+
+            WhatsAppEvent::DownloadProgress(message_id, received, total) => {
+                self.downloads.insert(message_id, (received, total));
+                self.status_message = format!(
+                    "Downloading: {}% ({} / {})",
+                    received * 100 / total.max(1),
+                    format_size(received),
+                    format_size(total),
+                );
+            }
+
+            WhatsAppEvent::MediaDownloaded(message_id, path, sha256) => {
+                self.downloads.remove(&message_id);
+                self.status_message = format!("Saved {}", path.display());
+
+                if let Some(store) = &self.store {
+                    let saved = media::DownloadedMedia { message_id: message_id.clone(), sha256, path: path.clone() };
+                    if let Err(e) = store.save_media(&saved) {
+                        log::warn!("Failed to record saved media: {}", e);
+                    }
+                }
+                if self.open_after_download.remove(&message_id) {
+                    self.open_media(&path);
+                }
+                let previewable = self.messages.values()
+                    .flatten()
+                    .any(|m| m.id == message_id && has_preview(m));
+                if previewable {
+                    self.load_preview(message_id.clone(), path.clone());
+                }
+                self.downloaded.insert(message_id, path);
+            }
+
+            WhatsAppEvent::DownloadFailed(message_id, error) => {
+                log::error!("Failed to download media for {}: {}", message_id, error);
+                self.downloads.remove(&message_id);
+                self.open_after_download.remove(&message_id);
+                self.graphics.set_image(ImageKey::Preview(message_id), None);
+                self.status_message = format!("⚠️  Download failed: {}", error);
+            }
+
+            WhatsAppEvent::PreviewLoaded(message_id, preview) => {
+                self.graphics.set_image(ImageKey::Preview(message_id), preview);
+            }
+
+            WhatsAppEvent::ProfilePictureFetched(chat_id, data) => {
+                if let Some(store) = &self.store {
+                    if let Err(e) = store.save_avatar(&chat_id, data.as_deref()) {
+                        log::warn!("Failed to cache profile picture: {}", e);
+                    }
+                }
+                self.load_avatar(chat_id, data);
+            }
+
+            WhatsAppEvent::AvatarLoaded(chat_id, avatar) => {
+                self.graphics.set_image(ImageKey::Avatar(chat_id), avatar);
+            }
+
+            WhatsAppEvent::SearchResults(query, messages) => {
+                // Cached from now on, so they show up in local searches and in context
+                self.persist_messages(&messages);
+                if let Some(search) = self.search.as_mut().filter(|s| s.query.trim() == query) {
+                    let count = messages.len();
+                    add_search_results(search, messages);
+                    search.server_status = Some(format!("{} found on the server", count));
+                }
+            }
+
+            WhatsAppEvent::SearchTyped(query) => {
+                // Stale unless the query is still what was typed
+                if self.search.as_ref().is_some_and(|s| s.query.trim() == query) {
+                    self.search_locally();
+                }
+            }
+
+            WhatsAppEvent::SearchFailed(query, error) => {
+                log::warn!("Server search for '{}' failed: {}", query, error);
+                if let Some(search) = self.search.as_mut().filter(|s| s.query.trim() == query) {
+                    search.server_status = Some(format!("Server search failed: {}", error));
+                }
+            }
+
             WhatsAppEvent::Error(error_msg) => {
                 log::error!("Error event received: {}", error_msg);
                 self.status_message = format!("⚠️  {}", error_msg);
@@ -291,11 +722,125 @@ impl App {
             Event::Mouse(mouse) => {
                 self.handle_mouse(mouse).await
             }
+            // Bracketed paste: the whole text at once, newlines included
+            Event::Paste(text) => {
+                self.handle_paste(&text);
+                Ok(false)
+            }
+            Event::Resize(_, _) => {
+                self.graphics.resize();
+                Ok(false)
+            }
             _ => Ok(false),
         }
     }
     
+    /// Pasted text goes where typing would, in the same order as `handle_key`
+    fn handle_paste(&mut self, text: &str) {
+        // Prompts are one line
+        let line = || text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+        if self.reaction_picker.is_some() || self.delete_prompt || self.message_details || self.message_menu.is_some() {
+            return;
+        }
+        if let Some(picker) = &mut self.forward_picker {
+            picker.paste(text);
+            return;
+        }
+        if let Some(search) = &mut self.search {
+            search.query.push_str(&line());
+            self.schedule_search();
+            return;
+        }
+        if let Some(filter) = &mut self.chat_filter {
+            filter.query.push_str(&line());
+            self.select_top_match();
+            return;
+        }
+
+        self.focused = FocusedWidget::Input;
+        self.composer.paste(text);
+    }
+
     async fn handle_key(&mut self, key: KeyEvent) -> Result<bool> {
+        self.theme_error = None;
+
+        // Popups capture every key while open
+        if self.reaction_picker.is_some() {
+            self.handle_reaction_picker_key(key);
+            return Ok(false);
+        }
+        if self.delete_prompt {
+            self.handle_delete_prompt_key(key);
+            return Ok(false);
+        }
+        if self.message_details {
+            self.message_details = false;
+            return Ok(false);
+        }
+        if let Some(menu) = &mut self.message_menu {
+            match menu.input(key) {
+                MenuInput::Open => {}
+                MenuInput::Close => self.message_menu = None,
+                MenuInput::Run(action) => {
+                    self.message_menu = None;
+                    self.run_message_action(action);
+                }
+                MenuInput::OpenLink(url) => {
+                    self.message_menu = None;
+                    self.open_link(&url);
+                }
+            }
+            return Ok(false);
+        }
+        if let Some(picker) = &mut self.forward_picker {
+            match picker.input(key, &self.chats) {
+                PickerInput::Open => {}
+                PickerInput::Close => self.forward_picker = None,
+                PickerInput::Forward(message_ids, chat_ids) => {
+                    self.forward_picker = None;
+                    self.forward(message_ids, chat_ids);
+                }
+            }
+            return Ok(false);
+        }
+
+        // Ctrl+F searches the messages of every chat
+        if key.code == KeyCode::Char('f') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.close_chat_filter();
+            self.search = Some(Search {
+                query: String::new(),
+                results: Vec::new(),
+                list_state: ListState::default(),
+                server_status: None,
+            });
+            return Ok(false);
+        }
+        if self.search.is_some() {
+            return self.handle_search_key(key).await;
+        }
+
+        // The filter prompt takes all typing while open
+        if self.chat_filter.is_some() {
+            return self.handle_chat_filter_key(key).await;
+        }
+
+        // Outbox actions for failed messages in the current chat. Not while
+        // typing: the composer keeps tui-textarea's redo and cut
+        if key.modifiers.contains(KeyModifiers::CONTROL) && self.focused != FocusedWidget::Input {
+            match key.code {
+                KeyCode::Char('r') => {
+                    self.retry_failed_messages();
+                    return Ok(false);
+                }
+                KeyCode::Char('x') => {
+                    self.discard_failed_messages();
+                    return Ok(false);
+                }
+                _ => {}
+            }
+        }
+
         // Tab cycles focus
         if key.code == KeyCode::Tab {
             self.focused = match self.focused {
@@ -309,6 +854,11 @@ impl App {
         // Route based on current focus
         match self.focused {
             FocusedWidget::ChatList => {
+                // `/` filters the chats
+                if key.code == KeyCode::Char('/') {
+                    self.open_chat_filter();
+                    return Ok(false);
+                }
                 // Only j/k/up/down navigate chat list
                 if matches!(key.code, KeyCode::Up | KeyCode::Down | KeyCode::Char('j') | KeyCode::Char('k')) {
                     return self.handle_chat_list_key(key).await;
@@ -318,11 +868,20 @@ impl App {
                 self.handle_input_key(key).await
             }
             FocusedWidget::MessageView => {
+                // A selected message captures keys for message actions
+                if self.selected_message_id.is_some() {
+                    return self.handle_selection_key(key).await;
+                }
                 // Arrow keys scroll messages
                 if matches!(key.code, KeyCode::Up | KeyCode::Down) {
                     self.handle_message_scroll(key);
                     return Ok(false);
                 }
+                // Enter starts selecting messages, newest first
+                if key.code == KeyCode::Enter {
+                    self.select_newest_message();
+                    return Ok(false);
+                }
                 // Any other key goes to input (auto-focus on typing)
                 self.focused = FocusedWidget::Input;
                 self.handle_input_key(key).await
@@ -351,6 +910,10 @@ impl App {
                     let clicked_index = self.chat_list_scroll + relative_y as usize;
 
                     // First item (index 0) is always "Archived Messages" - clicking toggles view
+                    // (or the filter prompt, which ignores clicks)
+                    if clicked_index == 0 && self.chat_filter.is_some() {
+                        return Ok(false);
+                    }
                     if clicked_index == 0 {
                         self.chat_list_view = match self.chat_list_view {
                             ChatListView::Normal => ChatListView::Archived,
@@ -362,24 +925,23 @@ impl App {
                         return Ok(false);
                     }
 
-                    // Get filtered chats for the current view
-                    let filtered_chats: Vec<_> = match self.chat_list_view {
-                        ChatListView::Normal => self.chats.iter().filter(|c| !c.archived).collect(),
-                        ChatListView::Archived => self.chats.iter().filter(|c| c.archived).collect(),
-                    };
-
                     // Calculate actual chat index (subtract 1 for "Archived Messages" offset)
                     let actual_chat_index = clicked_index.saturating_sub(1);
 
                     // Find the chat in the full chats list
-                    if let Some(clicked_chat) = filtered_chats.get(actual_chat_index) {
+                    if let Some(clicked_chat) = self.visible_chats().get(actual_chat_index) {
                         let chat_id = clicked_chat.id.clone();
 
+                        // A filter match opens in its own view
+                        if self.chat_filter.is_some() {
+                            self.open_filtered_chat(&chat_id).await?;
+                            return Ok(false);
+                        }
+
                         // Find this chat's absolute index in self.chats
                         if let Some(abs_index) = self.chats.iter().position(|c| c.id == chat_id) {
                             self.chat_list_state.select(Some(clicked_index));
                             self.focused = FocusedWidget::ChatList;
-                            self.input_buffer.clear();
                             self.message_scroll = 0;
                             self.load_chat_messages_background(abs_index).await?;
                         }
@@ -424,17 +986,7 @@ impl App {
                     }
                     FocusedWidget::MessageView => {
                         // Scroll up = show older messages (increase offset from bottom)
-                        if let Some(chat_id) = &self.current_chat_id {
-                            if let Some(messages) = self.messages.get(chat_id) {
-                                let num_lines = messages.len();
-                                let available_height = self.message_view_area.height.saturating_sub(2) as usize;
-                                let max_scroll = num_lines.saturating_sub(available_height);
-                                
-                                if (self.message_scroll as usize) < max_scroll {
-                                    self.message_scroll = self.message_scroll.saturating_add(3);
-                                }
-                            }
-                        }
+                        self.message_scroll = self.message_scroll.saturating_add(3).min(self.message_max_scroll);
                     }
                     _ => {}
                 }
@@ -451,18 +1003,17 @@ impl App {
                 // Scroll up = show older messages (increase offset from bottom)
                 if let Some(chat_id) = &self.current_chat_id {
                     if let Some(messages) = self.messages.get(chat_id) {
-                        let num_lines = messages.len();
-                        let available_height = self.message_view_area.height.saturating_sub(2) as usize;
-                        let max_scroll = num_lines.saturating_sub(available_height);
+                        let num_messages = messages.len();
+                        let max_scroll = self.message_max_scroll;
 
-                        if (self.message_scroll as usize) < max_scroll {
+                        if self.message_scroll < max_scroll {
                             self.message_scroll = self.message_scroll.saturating_add(1);
                             
                             // Pagination: Check if we're near the top (within 5 lines of oldest message)
-                            if (self.message_scroll as usize) >= max_scroll.saturating_sub(5) {
+                            if self.message_scroll >= max_scroll.saturating_sub(5) {
                                 let is_loading = self.loading_more_messages.get(chat_id).copied().unwrap_or(false);
-                                if !is_loading && num_lines >= 100 {  // Only paginate if we have at least initial load (was 50)
-                                    self.load_more_messages(chat_id.clone(), num_lines);
+                                if !is_loading && num_messages >= 100 {  // Only paginate if we have at least initial load (was 50)
+                                    self.load_more_messages(chat_id.clone(), num_messages);
                                 }
                             }
                         }
@@ -477,116 +1028,800 @@ impl App {
         }
     }
     
-    async fn handle_chat_list_key(&mut self, key: KeyEvent) -> Result<bool> {
+    /// Keys while a message is selected in the message view
+    async fn handle_selection_key(&mut self, key: KeyEvent) -> Result<bool> {
         match key.code {
-            KeyCode::Down | KeyCode::Char('j') => {
-                let i = match self.chat_list_state.selected() {
-                    Some(i) => {
-                        if i >= self.chats.len().saturating_sub(1) {
-                            i
-                        } else {
-                            i + 1
-                        }
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Esc if !self.marked_messages.is_empty() => self.marked_messages.clear(),
+            KeyCode::Esc => self.selected_message_id = None,
+
+            // Space marks messages to forward several at once
+            KeyCode::Char(' ') => {
+                if let Some(id) = self.selected_message().filter(|m| !m.deleted).map(|m| m.id.clone()) {
+                    if !self.marked_messages.remove(&id) {
+                        self.marked_messages.insert(id);
                     }
-                    None => 0,
-                };
-                self.chat_list_state.select(Some(i));
-                
-                // Auto-adjust scroll to keep selection visible
-                let visible_height = self.chat_list_area.height.saturating_sub(2) as usize; // -2 for borders
-                if i >= self.chat_list_scroll + visible_height {
-                    self.chat_list_scroll = i.saturating_sub(visible_height - 1);
+                    self.move_selection(1);
                 }
-                
-                // Clear input and reset scroll when changing chats
-                self.input_buffer.clear();
-                self.message_scroll = 0;
-                
-                // Load messages in background immediately
-                self.load_chat_messages_background(i).await?;
             }
-            
-            KeyCode::Up | KeyCode::Char('k') => {
-                let i = match self.chat_list_state.selected() {
-                    Some(i) => {
-                        if i == 0 {
-                            0
-                        } else {
-                            i - 1
-                        }
-                    }
-                    None => 0,
-                };
-                self.chat_list_state.select(Some(i));
-                
-                // Auto-adjust scroll to keep selection visible
-                if i < self.chat_list_scroll {
-                    self.chat_list_scroll = i;
+
+            // Enter lists what can be done with the message
+            KeyCode::Enter => {
+                self.message_menu = self.selected_message().map(MessageMenu::actions);
+            }
+
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                if let Some(action) = MessageAction::ALL.into_iter().find(|a| a.key() == c) {
+                    self.run_message_action(action);
                 }
-                
-                // Clear input and reset scroll when changing chats
-                self.input_buffer.clear();
-                self.message_scroll = 0;
-                
-                // Load messages in background immediately
-                self.load_chat_messages_background(i).await?;
             }
-            
+
             _ => {}
         }
-        
+
         Ok(false)
     }
-    
-    async fn handle_input_key(&mut self, key: KeyEvent) -> Result<bool> {
-        match key.code {
-            KeyCode::Enter => {
-                // Enter ONLY sends messages (or will handle multi-line in future)
-                if !self.input_buffer.is_empty() {
-                    return self.send_current_message().await;
+
+    fn run_message_action(&mut self, action: MessageAction) {
+        match action {
+            MessageAction::Copy => {
+                if let Some(msg) = self.selected_message().filter(|m| !m.deleted && !m.body.is_empty()) {
+                    self.clipboard = Some(msg.body.clone());
+                    self.status_message = "Copied to the clipboard".to_string();
+                }
+            }
+
+            // Reply to the selected message
+            MessageAction::Reply => {
+                if let Some(msg) = self.selected_message().cloned() {
+                    if self.editing.take().is_some() {
+                        self.composer.clear();
+                    }
+                    self.reply_to = Some(msg);
+                    self.selected_message_id = None;
+                    self.focused = FocusedWidget::Input;
+                }
+            }
+
+            // Edit one of our own messages in the composer
+            MessageAction::Edit => {
+                match self.selected_message().cloned() {
+                    Some(msg) if msg.from_me && !msg.deleted && !msg.has_media => {
+                        self.composer.set_text(&msg.body);
+                        self.reply_to = None;
+                        self.editing = Some(msg);
+                        self.selected_message_id = None;
+                        self.focused = FocusedWidget::Input;
+                    }
+                    Some(_) => self.status_message = "Only your own text messages can be edited".to_string(),
+                    None => {}
+                }
+            }
+
+            // Save the attachment, or open it (downloading first if needed)
+            MessageAction::Save => {
+                if let Some(msg) = self.selected_message().filter(|m| m.has_media).cloned() {
+                    self.start_download(&msg, false);
+                }
+            }
+            MessageAction::Open => {
+                if let Some(msg) = self.selected_message().filter(|m| m.has_media).cloned() {
+                    self.start_download(&msg, true);
+                }
+            }
+
+            // Ask how to delete the selected message
+            MessageAction::Delete => {
+                if self.selected_message().is_some_and(|m| !m.deleted) {
+                    self.delete_prompt = true;
+                }
+            }
+
+            // A single link opens right away, several are listed to pick from
+            MessageAction::OpenLink => {
+                let links = self.selected_message().map(|m| message_popup::find_links(&m.body)).unwrap_or_default();
+                match links.as_slice() {
+                    [] => self.status_message = "No links in this message".to_string(),
+                    [url] => self.open_link(url),
+                    _ => self.message_menu = Some(MessageMenu::links(links)),
+                }
+            }
+
+            MessageAction::GoToQuoted => self.jump_to_quoted(),
+
+            // Open the reaction picker on our current reaction, if any
+            MessageAction::React => {
+                let mine = self.selected_message()
+                    .and_then(|m| m.reactions.iter().find(|r| r.from_me))
+                    .and_then(|r| REACTION_EMOJIS.iter().position(|e| *e == r.emoji));
+                self.reaction_picker = Some(mine.unwrap_or(0));
+            }
+
+            MessageAction::Forward => self.open_forward_picker(),
+
+            MessageAction::Details => self.message_details = self.selected_message().is_some(),
+        }
+    }
+
+    /// Pick chats for the marked messages, or the selected one if none are marked
+    fn open_forward_picker(&mut self) {
+        let Some(messages) = self.current_chat_id.as_ref().and_then(|id| self.messages.get(id)) else {
+            return;
+        };
+        let message_ids: Vec<String> = if self.marked_messages.is_empty() {
+            self.selected_message().filter(|m| !m.deleted).map(|m| m.id.clone()).into_iter().collect()
+        } else {
+            messages.iter()
+                .filter(|m| self.marked_messages.contains(&m.id))
+                .map(|m| m.id.clone())
+                .collect()
+        };
+        if message_ids.is_empty() {
+            return;
+        }
+
+        self.forward_picker = Some(ForwardPicker::new(message_ids));
+    }
+
+    fn forward(&mut self, message_ids: Vec<String>, chat_ids: Vec<String>) {
+        let names: Vec<&str> = chat_ids.iter()
+            .filter_map(|id| self.chats.iter().find(|c| c.id == *id))
+            .map(|c| c.name.as_str())
+            .collect();
+        self.status_message = match message_ids.len() {
+            1 => format!("Forwarding to {}", names.join(", ")),
+            n => format!("Forwarding {} messages to {}", n, names.join(", ")),
+        };
+        self.marked_messages.clear();
+
+        let client = self.client.clone();
+        let event_tx = self.event_tx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = client.forward_messages(&message_ids, &chat_ids).await {
+                log::error!("Failed to forward messages: {}", e);
+                let _ = event_tx.send(WhatsAppEvent::Error(format!("Failed to forward: {}", e))).await;
+            }
+        });
+    }
+
+    fn open_link(&mut self, url: &str) {
+        self.status_message = match media::open_url(url) {
+            Ok(()) => format!("Opened {}", url),
+            Err(e) => format!("⚠️  {:#}", e),
+        };
+    }
+
+    /// Keys while the reaction picker is open
+    fn handle_reaction_picker_key(&mut self, key: KeyEvent) {
+        let Some(index) = self.reaction_picker else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Left | KeyCode::Char('h') => {
+                self.reaction_picker = Some(index.saturating_sub(1));
+            }
+            KeyCode::Right | KeyCode::Char('l') => {
+                self.reaction_picker = Some((index + 1).min(REACTION_EMOJIS.len() - 1));
+            }
+            KeyCode::Char(c @ '1'..='6') => {
+                self.reaction_picker = None;
+                self.send_reaction(REACTION_EMOJIS[c as usize - '1' as usize]);
+            }
+            KeyCode::Enter => {
+                self.reaction_picker = None;
+                self.send_reaction(REACTION_EMOJIS[index]);
+            }
+            KeyCode::Esc => self.reaction_picker = None,
+            _ => {}
+        }
+    }
+
+    /// Keys while the delete prompt is open
+    fn handle_delete_prompt_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('m') => {
+                self.delete_prompt = false;
+                self.delete_selected_message(false);
+            }
+            KeyCode::Char('e') => {
+                self.delete_prompt = false;
+                if self.selected_message().is_some_and(|m| m.from_me) {
+                    self.delete_selected_message(true);
+                } else {
+                    self.status_message = "Only your own messages can be deleted for everyone".to_string();
+                }
+            }
+            KeyCode::Esc => self.delete_prompt = false,
+            _ => {}
+        }
+    }
+
+    fn delete_selected_message(&mut self, for_everyone: bool) {
+        let Some(message_id) = self.selected_message().map(|m| m.id.clone()) else {
+            return;
+        };
+
+        let client = self.client.clone();
+        let event_tx = self.event_tx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = client.delete_message(&message_id, for_everyone).await {
+                log::error!("Failed to delete message {}: {}", message_id, e);
+                let _ = event_tx.send(WhatsAppEvent::Error(format!("Failed to delete: {}", e))).await;
+            }
+        });
+    }
+
+    /// Download a message's attachment in the background unless it is already saved
+    fn start_download(&mut self, msg: &Message, open: bool) {
+        if let Some(path) = self.downloaded.get(&msg.id).filter(|p| p.exists()).cloned() {
+            if open {
+                self.open_media(&path);
+            } else {
+                self.status_message = format!("Already saved at {}", path.display());
+            }
+            return;
+        }
+
+        if open {
+            self.open_after_download.insert(msg.id.clone());
+        }
+        if self.downloads.contains_key(&msg.id) {
+            return;
+        }
+        self.downloads.insert(msg.id.clone(), (0, 0));
+
+        let client = self.client.clone();
+        let event_tx = self.event_tx.clone();
+        let media = self.media.clone();
+        let msg = msg.clone();
+        tokio::spawn(async move {
+            let saved = match client.download_media(&msg.id).await {
+                Ok(download) => {
+                    let msg = msg.clone();
+                    tokio::task::spawn_blocking(move || media.save(&msg, &download))
+                        .await
+                        .map_err(anyhow::Error::from)
+                        .and_then(|saved| saved)
+                }
+                Err(e) => Err(e),
+            };
+
+            let event = match saved {
+                Ok(saved) => WhatsAppEvent::MediaDownloaded(saved.message_id, saved.path, saved.sha256),
+                Err(e) => WhatsAppEvent::DownloadFailed(msg.id, format!("{:#}", e)),
+            };
+            let _ = event_tx.send(event).await;
+        });
+    }
+
+    /// Fetch a preview the last frame asked for: decode the saved file, or download it first
+    fn request_preview(&mut self, message_id: &str) {
+        if let Some(path) = self.downloaded.get(message_id).filter(|p| p.exists()).cloned() {
+            self.load_preview(message_id.to_string(), path);
+            return;
+        }
+
+        // Without auto-download the preview shows up once the user saves the file
+        let msg = self.current_chat_id.as_ref()
+            .and_then(|chat_id| self.messages.get(chat_id))
+            .and_then(|messages| messages.iter().find(|m| m.id == message_id))
+            .cloned();
+        if let Some(msg) = msg.filter(|_| self.media.auto_download()) {
+            self.start_download(&msg, false);
+        }
+    }
+
+    /// Fetch an avatar the last frame asked for, from the disk cache unless it's stale
+    fn request_avatar(&mut self, chat_id: &str) {
+        let cached = match self.store.as_ref().map(|s| s.load_avatar(chat_id)) {
+            Some(Ok(cached)) => cached,
+            Some(Err(e)) => {
+                log::warn!("Failed to load cached profile picture: {}", e);
+                None
+            }
+            None => None,
+        };
+        let stale = match cached {
+            Some((data, fetched_at)) if chrono::Utc::now().timestamp() - fetched_at < AVATAR_MAX_AGE_SECS => {
+                self.load_avatar(chat_id.to_string(), data);
+                return;
+            }
+            Some((data, _)) => data,
+            None => None,
+        };
+
+        // Offline, an outdated picture beats none
+        let client = self.client.clone();
+        let event_tx = self.event_tx.clone();
+        let chat_id = chat_id.to_string();
+        tokio::spawn(async move {
+            match client.get_profile_picture(&chat_id).await {
+                Ok(data) => {
+                    let _ = event_tx.send(WhatsAppEvent::ProfilePictureFetched(chat_id, data)).await;
+                }
+                Err(e) => {
+                    log::warn!("Failed to fetch profile picture of {}: {:#}", chat_id, e);
+                    let avatar = tokio::task::spawn_blocking(move || decode_avatar(&chat_id, stale)).await;
+                    if let Ok(loaded) = avatar {
+                        let _ = event_tx.send(loaded).await;
+                    }
+                }
+            }
+        });
+    }
+
+    /// Decode a profile picture into an avatar in the background
+    fn load_avatar(&self, chat_id: String, data: Option<Vec<u8>>) {
+        let event_tx = self.event_tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let _ = event_tx.blocking_send(decode_avatar(&chat_id, data));
+        });
+    }
+
+    /// Decode a saved image into a thumbnail in the background
+    fn load_preview(&self, message_id: String, path: PathBuf) {
+        let event_tx = self.event_tx.clone();
+        let max = self.graphics.max_preview_px();
+        tokio::task::spawn_blocking(move || {
+            let preview = match graphics::load_preview(&path, max) {
+                Ok(image) => Some(Arc::new(image)),
+                Err(e) => {
+                    log::warn!("No preview for {}: {:#}", message_id, e);
+                    None
+                }
+            };
+            let _ = event_tx.blocking_send(WhatsAppEvent::PreviewLoaded(message_id, preview));
+        });
+    }
+
+    fn open_media(&mut self, path: &std::path::Path) {
+        self.status_message = match media::open_file(path) {
+            Ok(()) => format!("Opened {}", path.display()),
+            Err(e) => format!("⚠️  {:#}", e),
+        };
+    }
+
+    /// React to the selected message; picking our current reaction again removes it
+    fn send_reaction(&mut self, emoji: &str) {
+        let Some(msg) = self.selected_message() else {
+            return;
+        };
+
+        let already_mine = msg.reactions.iter().any(|r| r.from_me && r.emoji == emoji);
+        let emoji = if already_mine { String::new() } else { emoji.to_string() };
+        let message_id = msg.id.clone();
+
+        let client = self.client.clone();
+        let event_tx = self.event_tx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = client.react(&message_id, &emoji).await {
+                log::error!("Failed to react to message {}: {}", message_id, e);
+                let _ = event_tx.send(WhatsAppEvent::Error(format!("Failed to react: {}", e))).await;
+            }
+        });
+    }
+
+    fn selected_message(&self) -> Option<&Message> {
+        let chat_id = self.current_chat_id.as_ref()?;
+        let selected_id = self.selected_message_id.as_ref()?;
+        self.messages.get(chat_id)?.iter().find(|m| &m.id == selected_id)
+    }
+
+    fn select_newest_message(&mut self) {
+        let newest = self.current_chat_id.as_ref()
+            .and_then(|chat_id| self.messages.get(chat_id))
+            .and_then(|msgs| msgs.last())
+            .map(|m| m.id.clone());
+
+        if newest.is_some() {
+            self.selected_message_id = newest;
+            self.follow_selection = true;
+        }
+    }
+
+    /// Move the message cursor by `delta` messages (negative = older)
+    fn move_selection(&mut self, delta: isize) {
+        let Some(messages) = self.current_chat_id.as_ref().and_then(|id| self.messages.get(id)) else {
+            return;
+        };
+        let Some(index) = messages.iter().position(|m| Some(&m.id) == self.selected_message_id.as_ref()) else {
+            return;
+        };
+
+        let new_index = index.saturating_add_signed(delta).min(messages.len().saturating_sub(1));
+        self.selected_message_id = Some(messages[new_index].id.clone());
+        self.follow_selection = true;
+    }
+
+    fn jump_to_quoted(&mut self) {
+        let Some(quoted_id) = self.selected_message().and_then(|m| m.quoted.as_ref()).map(|q| q.id.clone()) else {
+            return;
+        };
+
+        let loaded = self.current_chat_id.as_ref()
+            .and_then(|chat_id| self.messages.get(chat_id))
+            .is_some_and(|msgs| msgs.iter().any(|m| m.id == quoted_id));
+
+        if loaded {
+            self.selected_message_id = Some(quoted_id);
+            self.follow_selection = true;
+        } else {
+            self.status_message = "Original message is not loaded - scroll up to load older messages".to_string();
+        }
+    }
+    
+    async fn handle_search_key(&mut self, key: KeyEvent) -> Result<bool> {
+        let Some(search) = &mut self.search else {
+            return Ok(false);
+        };
+
+        match key.code {
+            KeyCode::Esc => self.search = None,
+
+            KeyCode::Enter => {
+                let selected = search.list_state.selected().and_then(|i| search.results.get(i)).cloned();
+                if let Some(msg) = selected {
+                    self.open_search_result(msg).await?;
+                }
+            }
+
+            KeyCode::Up => {
+                let i = search.list_state.selected().unwrap_or(0);
+                search.list_state.select(Some(i.saturating_sub(1)));
+            }
+
+            KeyCode::Down => {
+                let last = search.results.len().saturating_sub(1);
+                let i = search.list_state.selected().map_or(0, |i| (i + 1).min(last));
+                search.list_state.select(Some(i));
+            }
+
+            // Messages that were never loaded here only exist on the server
+            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let query = search.query.trim().to_string();
+                if query.is_empty() {
+                    return Ok(false);
+                }
+                search.server_status = Some("Searching the server...".to_string());
+
+                let client = self.client.clone();
+                let event_tx = self.event_tx.clone();
+                tokio::spawn(async move {
+                    let event = match client.search_messages(&query, SEARCH_LIMIT).await {
+                        Ok(messages) => WhatsAppEvent::SearchResults(query, messages),
+                        Err(e) => WhatsAppEvent::SearchFailed(query, format!("{:#}", e)),
+                    };
+                    let _ = event_tx.send(event).await;
+                });
+            }
+
+            KeyCode::Backspace => {
+                search.query.pop();
+                self.schedule_search();
+            }
+
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                search.query.push(c);
+                self.schedule_search();
+            }
+
+            _ => {}
+        }
+
+        Ok(false)
+    }
+
+    /// Search once typing pauses instead of on every key
+    fn schedule_search(&mut self) {
+        let Some(query) = self.search.as_ref().map(|s| s.query.trim().to_string()) else {
+            return;
+        };
+        let event_tx = self.event_tx.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(SEARCH_DEBOUNCE).await;
+            let _ = event_tx.send(WhatsAppEvent::SearchTyped(query)).await;
+        });
+    }
+
+    /// Results for the current query from loaded messages and the persisted history
+    fn search_locally(&mut self) {
+        let Some(query) = self.search.as_ref().map(|s| s.query.trim().to_string()) else {
+            return;
+        };
+
+        let mut results = Vec::new();
+        if !query.is_empty() {
+            results.extend(self.messages.values()
+                .flatten()
+                .filter(|m| !m.deleted && find_ignore_case(&m.body, &query).is_some())
+                .cloned());
+            if let Some(store) = &self.store {
+                match store.search_messages(&query, SEARCH_LIMIT) {
+                    Ok(found) => results.extend(found),
+                    Err(e) => log::warn!("Failed to search cached messages: {}", e),
+                }
+            }
+        }
+
+        if let Some(search) = &mut self.search {
+            search.results.clear();
+            search.server_status = None;
+            add_search_results(search, results);
+        }
+    }
+
+    /// Close the search and show the message among its neighbours, selected
+    async fn open_search_result(&mut self, msg: Message) -> Result<()> {
+        self.search = None;
+        let Some(index) = self.chats.iter().position(|c| c.id == msg.chat_id) else {
+            self.status_message = "That chat is not in the chat list".to_string();
+            return Ok(());
+        };
+        self.show_chat_in_list(index);
+        self.message_scroll = 0;
+        self.load_chat_messages_background(index).await?;
+
+        // Cached history from a little before the message, and the message itself even if it's not cached
+        let mut context = match &self.store {
+            Some(store) => store.load_messages_around(&msg.chat_id, msg.timestamp, SEARCH_CONTEXT, SEARCH_FOLLOWING).unwrap_or_else(|e| {
+                log::warn!("Failed to load messages around a search result: {}", e);
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
+        context.push(msg.clone());
+        self.merge_messages(&msg.chat_id, context);
+
+        self.selected_message_id = Some(msg.id);
+        self.follow_selection = true;
+        self.focused = FocusedWidget::MessageView;
+        Ok(())
+    }
+
+    async fn handle_chat_filter_key(&mut self, key: KeyEvent) -> Result<bool> {
+        match key.code {
+            KeyCode::Esc => self.close_chat_filter(),
+
+            // Open the highlighted match, the best one unless moved with the arrows
+            KeyCode::Enter => {
+                if let Some(chat_id) = self.selected_chat_id() {
+                    self.open_filtered_chat(&chat_id).await?;
+                }
+            }
+
+            KeyCode::Up => {
+                if let Some(i) = self.chat_list_state.selected().filter(|&i| i > 1) {
+                    self.chat_list_state.select(Some(i - 1));
+                    self.chat_list_scroll = self.chat_list_scroll.min(i - 1);
+                }
+            }
+
+            KeyCode::Down => {
+                let last = self.visible_chat_indices().len();
+                if let Some(i) = self.chat_list_state.selected().filter(|&i| i < last) {
+                    self.chat_list_state.select(Some(i + 1));
+                    let visible_height = self.chat_list_area.height.saturating_sub(2).max(1) as usize;
+                    if i + 1 >= self.chat_list_scroll + visible_height {
+                        self.chat_list_scroll = i + 2 - visible_height;
+                    }
+                }
+            }
+
+            KeyCode::Backspace => {
+                if let Some(filter) = &mut self.chat_filter {
+                    filter.query.pop();
+                }
+                self.select_top_match();
+            }
+
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                if let Some(filter) = &mut self.chat_filter {
+                    filter.query.push(c);
+                }
+                self.select_top_match();
+            }
+
+            _ => {}
+        }
+
+        Ok(false)
+    }
+
+    async fn handle_chat_list_key(&mut self, key: KeyEvent) -> Result<bool> {
+        match key.code {
+            KeyCode::Down | KeyCode::Char('j') => {
+                let i = match self.chat_list_state.selected() {
+                    Some(i) => {
+                        if i >= self.visible_chats().len() {
+                            i
+                        } else {
+                            i + 1
+                        }
+                    }
+                    None => 0,
+                };
+                self.chat_list_state.select(Some(i));
+                
+                // Auto-adjust scroll to keep selection visible
+                let visible_height = self.chat_list_area.height.saturating_sub(2) as usize; // -2 for borders
+                if i >= self.chat_list_scroll + visible_height {
+                    self.chat_list_scroll = i.saturating_sub(visible_height - 1);
+                }
+                
+                // Reset scroll when changing chats
+                self.message_scroll = 0;
+                
+                // Load messages in background immediately
+                if let Some(index) = self.selected_chat_index() {
+                    self.load_chat_messages_background(index).await?;
                 }
             }
             
-            KeyCode::Char(c) => {
-                // All typing goes to input - input is always active
-                self.input_buffer.push(c);
+            KeyCode::Up | KeyCode::Char('k') => {
+                let i = match self.chat_list_state.selected() {
+                    Some(i) => {
+                        if i == 0 {
+                            0
+                        } else {
+                            i - 1
+                        }
+                    }
+                    None => 0,
+                };
+                self.chat_list_state.select(Some(i));
+                
+                // Auto-adjust scroll to keep selection visible
+                if i < self.chat_list_scroll {
+                    self.chat_list_scroll = i;
+                }
+                
+                // Reset scroll when changing chats
+                self.message_scroll = 0;
+                
+                // Load messages in background immediately
+                if let Some(index) = self.selected_chat_index() {
+                    self.load_chat_messages_background(index).await?;
+                }
             }
             
-            KeyCode::Backspace => {
-                if self.input_buffer.is_empty() {
-                    self.focused = FocusedWidget::ChatList;
-                } else {
-                    self.input_buffer.pop();
+            _ => {}
+        }
+        
+        Ok(false)
+    }
+    
+    async fn handle_input_key(&mut self, key: KeyEvent) -> Result<bool> {
+        match key.code {
+            // Long messages are easier to write in a real editor
+            KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.editor_requested = true;
+            }
+
+            // Shift+Enter (where the terminal reports it) or Alt+Enter starts a new line
+            KeyCode::Enter if key.modifiers.intersects(KeyModifiers::SHIFT | KeyModifiers::ALT) => {
+                self.composer.insert_newline();
+            }
+
+            // Plain Enter sends
+            KeyCode::Enter => {
+                if self.composer.is_empty() && self.attachment.is_none() {
+                    return Ok(false);
+                }
+                if self.editing.is_some() {
+                    self.send_edit();
+                    return Ok(false);
                 }
+                return self.send_current_message().await;
+            }
+            
+            KeyCode::Backspace if self.composer.is_empty() => {
+                self.focused = FocusedWidget::ChatList;
             }
             
             KeyCode::Esc => {
-                // Clear input buffer
-                // Clear input buffer
-                if self.input_buffer.is_empty() {
-                    self.focused = FocusedWidget::ChatList;
+                // Clear input buffer (cancelling an edit), then the attachment, then the reply, then leave the input
+                if self.editing.take().is_some() || !self.composer.is_empty() {
+                    self.composer.clear();
+                } else if self.attachment.is_some() {
+                    self.attachment = None;
+                } else if self.reply_to.is_some() {
+                    self.reply_to = None;
                 } else {
-                    self.input_buffer.clear();
+                    self.focused = FocusedWidget::ChatList;
                 }
             }
             
-            _ => {}
+            // Everything else edits the text
+            _ => {
+                self.composer.input(key);
+            }
         }
         
         Ok(false)
     }
     
+    /// The draft to open in $EDITOR, if Ctrl+E was pressed
+    pub fn take_editor_request(&mut self) -> Option<String> {
+        std::mem::take(&mut self.editor_requested).then(|| self.composer.text())
+    }
+
+    /// Load the text back from $EDITOR, None if it was closed without success
+    pub fn finish_editor(&mut self, edited: Result<Option<String>>) {
+        self.focused = FocusedWidget::Input;
+        match edited {
+            Ok(Some(text)) => self.composer.set_text(&text),
+            Ok(None) => self.status_message = "Editor exited with an error, draft unchanged".to_string(),
+            Err(e) => {
+                log::error!("External editor failed: {:#}", e);
+                self.status_message = format!("⚠️  {:#}", e);
+            }
+        }
+    }
+
+    /// Keep the composer text as the open chat's draft, in memory and on disk
+    pub fn stash_draft(&mut self) {
+        // The text of a message being edited isn't a draft
+        let Some(chat_id) = self.current_chat_id.clone().filter(|_| self.editing.is_none()) else {
+            return;
+        };
+        let text = self.composer.text();
+        if let Some(store) = &self.store {
+            if let Err(e) = store.save_draft(&chat_id, &text) {
+                log::warn!("Failed to save draft: {}", e);
+            }
+        }
+        if text.is_empty() {
+            self.drafts.remove(&chat_id);
+        } else {
+            self.drafts.insert(chat_id, text);
+        }
+    }
+
     async fn load_chat_messages_background(&mut self, chat_index: usize) -> Result<()> {
         if let Some(chat) = self.chats.get(chat_index) {
             let chat_id = chat.id.clone();
             let chat_name = chat.name.clone();
 
+            // Selection, reply and edit belong to the previous chat, the draft waits there
+            if self.current_chat_id.as_ref() != Some(&chat_id) {
+                self.selected_message_id = None;
+                self.reaction_picker = None;
+                self.delete_prompt = false;
+                self.message_menu = None;
+                self.message_details = false;
+                self.marked_messages.clear();
+                self.forward_picker = None;
+                self.reply_to = None;
+                if self.editing.take().is_some() {
+                    self.composer.clear();
+                }
+                self.stash_draft();
+                let draft = self.drafts.remove(&chat_id).unwrap_or_default();
+                self.composer.set_text(&draft);
+            }
+
             // Set as current chat immediately
             self.current_chat_id = Some(chat_id.clone());
 
             // Load messages if not cached
             if !self.messages.contains_key(&chat_id) {
-                self.status_message = format!("Loading {} messages...", chat_name);
+                // Show persisted history right away, the service reconciles it below
+                let cached = match &self.store {
+                    Some(store) => store.load_messages(&chat_id, 100).unwrap_or_else(|e| {
+                        log::warn!("Failed to load cached messages: {}", e);
+                        Vec::new()
+                    }),
+                    None => Vec::new(),
+                };
+
+                if cached.is_empty() {
+                    self.status_message = format!("Loading {} messages...", chat_name);
+                } else {
+                    self.status_message = format!("{} - {} messages (cached, syncing...)", chat_name, cached.len());
+                    self.messages.insert(chat_id.clone(), cached);
+                }
                 log::info!("Loading messages for chat: {}", chat_name);
 
                 // Spawn non-blocking task to load messages
@@ -620,6 +1855,8 @@ impl App {
             // Mark as read
             if let Some(chat) = self.chats.get_mut(chat_index) {
                 chat.unread_count = 0;
+                let chat_id = chat.id.clone();
+                self.persist_chat(&chat_id);
             }
         }
 
@@ -670,96 +1907,423 @@ impl App {
 
             let client = self.client.clone();
             let event_tx = self.event_tx.clone();
-            let chat_id_clone = chat_id.clone();
+            let chat_id_clone = chat_id.clone();
+
+            tokio::spawn(async move {
+                // Get full context (100 messages) to ensure we have everything
+                match client.get_messages(&chat_id_clone, 100).await {
+                    Ok(messages) => {
+                        log::debug!("Background sync loaded {} messages for {}", messages.len(), chat_id_clone);
+                        let _ = event_tx.send(WhatsAppEvent::MessagesLoaded(chat_id_clone, messages)).await;
+                    }
+                    Err(e) => {
+                        log::warn!("Background sync failed for {}: {}", chat_id_clone, e);
+                    }
+                }
+            });
+        }
+    }
+
+    /// Add messages to a chat's loaded ones: newer copies win, oldest first
+    fn merge_messages(&mut self, chat_id: &str, new_messages: Vec<Message>) {
+        if let Some(existing) = self.messages.get_mut(chat_id) {
+            let old_count = existing.len();
+            log::info!("Merging {} new messages with {} existing messages", new_messages.len(), old_count);
+            
+            // Prepend new messages (older ones) and deduplicate
+            let mut all_messages = new_messages;
+            all_messages.append(existing);
+            
+            // Deduplicate by message ID, keeping first occurrence
+            let mut seen = std::collections::HashSet::new();
+            all_messages.retain(|msg| seen.insert(msg.id.clone()));
+            
+            // Sort by timestamp (oldest first)
+            all_messages.sort_by_key(|m| m.timestamp);
+            
+            *existing = all_messages;
+            
+            let new_count = existing.len() - old_count;
+            log::info!("After merge: {} total messages ({} new)", existing.len(), new_count);
+        } else {
+            // Initial load
+            log::info!("Initial load of {} messages for chat {}", new_messages.len(), chat_id);
+            self.messages.insert(chat_id.to_string(), new_messages);
+        }
+    }
+
+    /// Write messages through to the persistent store
+    fn persist_messages(&mut self, messages: &[Message]) {
+        if let Some(store) = &mut self.store {
+            if let Err(e) = store.save_messages(messages) {
+                log::warn!("Failed to cache messages: {}", e);
+            }
+        }
+    }
+
+    /// Write a chat's current state through to the persistent store
+    fn persist_chat(&self, chat_id: &str) {
+        if let (Some(store), Some(chat)) = (&self.store, self.chats.iter().find(|c| c.id == chat_id)) {
+            if let Err(e) = store.save_chat(chat) {
+                log::warn!("Failed to cache chat: {}", e);
+            }
+        }
+    }
+
+    /// Look up a cached message, in the given chat first and then everywhere
+    /// (the service can report a different chat id for our own messages)
+    fn find_message_mut(&mut self, chat_id: &str, message_id: &str) -> Option<&mut Message> {
+        let in_chat = self.messages.get(chat_id)
+            .is_some_and(|msgs| msgs.iter().any(|m| m.id == message_id));
+
+        if in_chat {
+            self.messages.get_mut(chat_id)?.iter_mut().find(|m| m.id == message_id)
+        } else {
+            self.messages.values_mut().flatten().find(|m| m.id == message_id)
+        }
+    }
+
+    /// Chats shown in the chat list: the current view, or the filter's matches best first
+    fn visible_chats(&self) -> Vec<&Chat> {
+        self.visible_chat_indices().into_iter().map(|i| &self.chats[i]).collect()
+    }
+
+    /// Positions in `self.chats` of the chats shown in the chat list
+    fn visible_chat_indices(&self) -> Vec<usize> {
+        if let Some(filter) = &self.chat_filter {
+            // Every chat, archived or not; ties keep the most recent first
+            let mut matches: Vec<(i64, usize)> = self.chats.iter()
+                .enumerate()
+                .filter_map(|(i, chat)| fuzzy::chat_score(&filter.query, chat).map(|score| (score, i)))
+                .collect();
+            matches.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+            return matches.into_iter().map(|(_, i)| i).collect();
+        }
+
+        let archived = self.chat_list_view == ChatListView::Archived;
+        (0..self.chats.len()).filter(|&i| self.chats[i].archived == archived).collect()
+    }
+
+    /// Position in `self.chats` of the chat under the chat list cursor
+    fn selected_chat_index(&self) -> Option<usize> {
+        let chat_id = self.selected_chat_id()?;
+        self.chats.iter().position(|c| c.id == chat_id)
+    }
+
+    /// Open the filter prompt in place of "Archived Messages"
+    fn open_chat_filter(&mut self) {
+        self.chat_filter = Some(ChatFilter {
+            query: String::new(),
+            view: self.chat_list_view,
+            selected: self.selected_chat_id(),
+            scroll: self.chat_list_scroll,
+        });
+        self.focused = FocusedWidget::ChatList;
+        self.select_top_match();
+    }
+
+    /// Esc: back to the list as it was before filtering
+    ///
+    /// The cursor goes back to the same chat even if chats moved meanwhile.
+    fn close_chat_filter(&mut self) {
+        if let Some(filter) = self.chat_filter.take() {
+            self.chat_list_view = filter.view;
+            self.chat_list_state.select(Some(0));
+            if let Some(chat_id) = &filter.selected {
+                self.select_chat_by_id(chat_id);
+            }
+            let selected = self.chat_list_state.selected().unwrap_or(0);
+            let visible_height = self.chat_list_area.height.saturating_sub(2).max(1) as usize;
+            self.chat_list_scroll = filter.scroll.clamp((selected + 1).saturating_sub(visible_height), selected);
+        }
+    }
+
+    /// Highlight the best match, the one Enter opens
+    fn select_top_match(&mut self) {
+        let any = !self.visible_chat_indices().is_empty();
+        self.chat_list_state.select(any.then_some(1));
+        self.chat_list_scroll = 0;
+    }
+
+    /// Select a chat in its own view (archived or not) and scroll it into sight
+    fn show_chat_in_list(&mut self, index: usize) {
+        self.chat_list_view = if self.chats[index].archived { ChatListView::Archived } else { ChatListView::Normal };
+        let chat_id = self.chats[index].id.clone();
+        self.select_chat_by_id(&chat_id);
+
+        let visible_height = self.chat_list_area.height.saturating_sub(2).max(1) as usize;
+        let selected = self.chat_list_state.selected().unwrap_or(0);
+        self.chat_list_scroll = selected.saturating_sub(visible_height - 1);
+    }
+
+    /// Leave the filter for the chat's own view, with the chat selected and open
+    async fn open_filtered_chat(&mut self, chat_id: &str) -> Result<()> {
+        let Some(index) = self.chats.iter().position(|c| c.id == chat_id) else {
+            return Ok(());
+        };
+        self.chat_filter = None;
+        self.show_chat_in_list(index);
+        self.message_scroll = 0;
+        self.focused = FocusedWidget::Input;
+        self.load_chat_messages_background(index).await
+    }
+
+    /// ID of the chat under the chat list cursor (None for "Archived Messages")
+    fn selected_chat_id(&self) -> Option<String> {
+        let visual_index = self.chat_list_state.selected()?;
+        // Visual index 1+ maps to visible chats index 0+
+        let index = visual_index.checked_sub(1)?;
+        self.visible_chats().get(index).map(|c| c.id.clone())
+    }
+
+    /// Move the chat list cursor to a chat, if it is in the current view
+    fn select_chat_by_id(&mut self, chat_id: &str) {
+        if let Some(index) = self.visible_chats().iter().position(|c| c.id == chat_id) {
+            // Convert to visual index (add 1 for "Archived Messages" offset)
+            self.chat_list_state.select(Some(index + 1));
+        }
+    }
+
+    /// Refresh messages for the current chat (useful for periodic sync)
+    pub async fn refresh_current_chat_messages(&mut self) -> Result<()> {
+        if let Some(chat_id) = self.current_chat_id.clone() {
+            log::debug!("Refreshing messages for current chat: {}", chat_id);
+
+            match self.client.get_messages(&chat_id, 100).await {
+                Ok(messages) => {
+                    let old_count = self.messages.get(&chat_id).map(|m| m.len()).unwrap_or(0);
+
+                    self.persist_messages(&messages);
+                    // Merged, so older history (paginated, quoted, around a search hit) stays loaded
+                    self.merge_messages(&chat_id, messages);
+
+                    let loaded = &self.messages[&chat_id];
+                    if self.selected_message_id.as_ref().is_some_and(|id| !loaded.iter().any(|m| &m.id == id)) {
+                        self.selected_message_id = None;
+                    }
+                    if loaded.len() > old_count {
+                        log::info!("Refreshed {} new messages", loaded.len() - old_count);
+                    }
+                }
+                Err(e) => {
+                    log::warn!("Failed to refresh messages: {}", e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+    
+    async fn send_current_message(&mut self) -> Result<bool> {
+        if let Some(chat_id) = self.current_chat_id.clone() {
+            let text = self.composer.text();
+
+            // `/attach <path>` picks a file for the next message
+            if let Some(path) = text.strip_prefix("/attach ") {
+                self.composer.clear();
+                self.attach_file(path);
+                return Ok(false);
+            }
+
+            // Attachments go straight to the service, the text is their caption
+            if let Some(upload) = self.attachment.take() {
+                self.composer.clear();
+                self.send_attachment(chat_id, upload, text);
+                return Ok(false);
+            }
+            
+            if text.is_empty() {
+                return Ok(false);
+            }
+            
+            // Clear input immediately for responsiveness
+            self.composer.clear();
+            
+            // Queue in the outbox: it survives disconnects and restarts until the service confirms it
+            let quoted_id = self.reply_to.take().map(|m| m.id);
+            let entry = OutboxEntry::new(&chat_id, &text, quoted_id);
+            self.save_outbox_entry(&entry);
+            self.outbox.push(entry);
+
+            // Reset scroll to auto-scroll to the new message
+            self.message_scroll = 0;
+
+            if self.state == AppState::Ready {
+                self.status_message = "Sending...".to_string();
+                self.flush_outbox();
+            } else {
+                self.status_message = "Offline - message queued, will send after reconnect".to_string();
+            }
+        }
+        
+        Ok(false)
+    }
+
+    /// Pick a local file to send with the next message
+    fn attach_file(&mut self, path: &str) {
+        let path = path.trim().trim_matches(|c| c == '"' || c == '\'');
+        match MediaUpload::from_path(&media::expand_home(path)) {
+            Ok(upload) => {
+                self.status_message = format!(
+                    "Attached {} ({}, {}) - type a caption or press Enter to send",
+                    upload.filename,
+                    upload.mimetype,
+                    format_size(upload.size),
+                );
+                self.attachment = Some(upload);
+            }
+            Err(e) => self.status_message = format!("⚠️  Cannot attach: {:#}", e),
+        }
+    }
+
+    /// Upload a file in the background; progress comes back as `UploadProgress` events
+    fn send_attachment(&mut self, chat_id: String, upload: MediaUpload, caption: String) {
+        if self.state != AppState::Ready {
+            self.attachment = Some(upload);
+            self.status_message = "Offline - attachments can only be sent while connected".to_string();
+            return;
+        }
+
+        let quoted_id = self.reply_to.take().map(|m| m.id);
+        self.message_scroll = 0;
+        self.status_message = format!("Uploading {}...", upload.filename);
+
+        let client = self.client.clone();
+        let event_tx = self.event_tx.clone();
+        tokio::spawn(async move {
+            let event = match client.send_media(&chat_id, &upload, &caption, quoted_id.as_deref()).await {
+                Ok(()) => WhatsAppEvent::MediaSent(upload.filename),
+                Err(e) => {
+                    log::error!("Failed to send {}: {:#}", upload.filename, e);
+                    WhatsAppEvent::Error(format!("Failed to send {}: {}", upload.filename, e))
+                }
+            };
+            let _ = event_tx.send(event).await;
+        });
+    }
+
+    /// Send the composer text as the new body of the message being edited
+    fn send_edit(&mut self) {
+        let Some(msg) = self.editing.take() else {
+            return;
+        };
+        let text = self.composer.text();
+        self.composer.clear();
+        if text == msg.body {
+            return;
+        }
+
+        let client = self.client.clone();
+        let event_tx = self.event_tx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = client.edit_message(&msg.id, &text).await {
+                log::error!("Failed to edit message {}: {}", msg.id, e);
+                let _ = event_tx.send(WhatsAppEvent::Error(format!("Failed to edit: {}", e))).await;
+            }
+        });
+    }
+
+    /// Start sending queued messages, one in flight per chat to keep them in order
+    ///
+    /// An entry backing off after a failure holds up the rest of its chat.
+    pub fn flush_outbox(&mut self) {
+        if self.state != AppState::Ready {
+            return;
+        }
+
+        for index in outbox::next_to_send(&self.outbox) {
+            let entry = &mut self.outbox[index];
+            entry.state = OutboxState::Sending;
+
+            let client = self.client.clone();
+            let event_tx = self.event_tx.clone();
+            let id = entry.id.clone();
+            let chat_id = entry.chat_id.clone();
+            let text = entry.text.clone();
+            let quoted_id = entry.quoted_id.clone();
 
             tokio::spawn(async move {
-                // Get full context (100 messages) to ensure we have everything
-                match client.get_messages(&chat_id_clone, 100).await {
-                    Ok(messages) => {
-                        log::debug!("Background sync loaded {} messages for {}", messages.len(), chat_id_clone);
-                        let _ = event_tx.send(WhatsAppEvent::MessagesLoaded(chat_id_clone, messages)).await;
-                    }
-                    Err(e) => {
-                        log::warn!("Background sync failed for {}: {}", chat_id_clone, e);
-                    }
-                }
+                // The entry id goes along so the service can tell a retry from a new message
+                let event = match client.send_message(&chat_id, &text, quoted_id.as_deref(), &id).await {
+                    Ok(()) => WhatsAppEvent::OutboxSent(id),
+                    Err(e) => WhatsAppEvent::OutboxFailed(id, format!("{:#}", e), outbox::is_permanent(&e)),
+                };
+                let _ = event_tx.send(event).await;
             });
         }
     }
 
-    /// Refresh messages for the current chat (useful for periodic sync)
-    pub async fn refresh_current_chat_messages(&mut self) -> Result<()> {
-        if let Some(chat_id) = &self.current_chat_id {
-            log::debug!("Refreshing messages for current chat: {}", chat_id);
-
-            match self.client.get_messages(chat_id, 100).await {
-                Ok(messages) => {
-                    let old_count = self.messages.get(chat_id).map(|m| m.len()).unwrap_or(0);
-                    let new_count = messages.len();
-
-                    self.messages.insert(chat_id.clone(), messages);
+    /// Requeue failed messages in the current chat
+    fn retry_failed_messages(&mut self) {
+        let Some(chat_id) = self.current_chat_id.clone() else {
+            return;
+        };
 
-                    if new_count > old_count {
-                        log::info!("Refreshed {} new messages", new_count - old_count);
-                    }
-                }
-                Err(e) => {
-                    log::warn!("Failed to refresh messages: {}", e);
-                }
+        let mut retried = Vec::new();
+        for entry in self.outbox.iter_mut() {
+            if entry.chat_id == chat_id && entry.state == OutboxState::Failed {
+                entry.reset();
+                retried.push(entry.clone());
             }
         }
+        if retried.is_empty() {
+            return;
+        }
 
-        Ok(())
+        for entry in &retried {
+            self.save_outbox_entry(entry);
+        }
+        self.status_message = format!("Retrying {} message(s)...", retried.len());
+        self.flush_outbox();
     }
-    
-    async fn send_current_message(&mut self) -> Result<bool> {
-        if let Some(chat_id) = &self.current_chat_id {
-            let text = self.input_buffer.clone();
-            
-            if text.is_empty() {
-                return Ok(false);
+
+    /// Drop failed messages in the current chat
+    fn discard_failed_messages(&mut self) {
+        let Some(chat_id) = self.current_chat_id.clone() else {
+            return;
+        };
+
+        let (discarded, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.outbox)
+            .into_iter()
+            .partition(|e| e.chat_id == chat_id && e.state == OutboxState::Failed);
+        self.outbox = kept;
+
+        for entry in &discarded {
+            self.remove_outbox_entry(&entry.id);
+        }
+        if !discarded.is_empty() {
+            self.status_message = format!("Discarded {} message(s)", discarded.len());
+        }
+    }
+
+    fn save_outbox_entry(&self, entry: &OutboxEntry) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.save_outbox_entry(entry) {
+                log::warn!("Failed to persist outbox entry: {}", e);
             }
-            
-            // Clear input immediately for responsiveness
-            self.input_buffer.clear();
-            
-            // Send message
-            match self.client.send_message(chat_id, &text).await {
-                Ok(_) => {
-                    log::info!("Message sent successfully");
-                    self.status_message = "Message sent".to_string();
-                    
-                    // Reset scroll to auto-scroll to the new message
-                    self.message_scroll = 0;
-                    
-                    // Refresh messages to show the sent message
-                    // This will be near-instant since the message was just sent
-                    if let Err(e) = self.refresh_current_chat_messages().await {
-                        log::warn!("Failed to refresh after send: {}", e);
-                    }
-                }
-                Err(e) => {
-                    log::error!("Failed to send message: {}", e);
-                    self.status_message = format!("Failed to send: {}", e);
-                    // Put the text back in the input buffer
-                    self.input_buffer = text;
-                }
+        }
+    }
+
+    fn remove_outbox_entry(&self, id: &str) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.remove_outbox_entry(id) {
+                log::warn!("Failed to remove outbox entry: {}", e);
             }
         }
-        
-        Ok(false)
     }
     
     /// Render the UI
     pub fn render(&mut self, frame: &mut Frame) {
+        self.graphics.begin_frame();
+
         // Show QR code during authentication
         if self.state == AppState::Authenticating && self.qr_code.is_some() {
             self.render_qr(frame);
             return;
         }
         
-        // Show loading screen if not ready or no chats loaded yet
-        if self.state != AppState::Ready || self.chats.is_empty() {
+        // Show loading screen until we have chats, live or cached
+        if self.chats.is_empty() {
             self.render_loading(frame);
             return;
         }
@@ -768,6 +2332,32 @@ impl App {
         self.render_main(frame);
     }
     
+    /// Draw images over the frame just rendered, and fetch the ones it was missing
+    pub fn flush_graphics(&mut self, out: &mut impl Write) -> Result<()> {
+        for key in self.graphics.take_missing() {
+            match key {
+                ImageKey::Preview(message_id) => self.request_preview(&message_id),
+                ImageKey::Avatar(chat_id) => self.request_avatar(&chat_id),
+            }
+        }
+        self.graphics.flush(out)
+    }
+
+    /// Copy pending clipboard text through the terminal (OSC 52), which works over SSH too
+    pub fn flush_clipboard(&mut self, out: &mut impl Write) -> Result<()> {
+        use base64::Engine;
+        if let Some(text) = self.clipboard.take() {
+            write!(out, "\x1b]52;c;{}\x07", base64::engine::general_purpose::STANDARD.encode(text))?;
+            out.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Remove images from the terminal before exiting
+    pub fn clear_graphics(&mut self, out: &mut impl Write) -> Result<()> {
+        self.graphics.clear(out)
+    }
+
     fn render_loading(&self, frame: &mut Frame) {
         let loading_text = vec![
             Line::from(""),
@@ -821,16 +2411,21 @@ impl App {
         // Render chat list
         self.render_chat_list(frame, chunks[0]);
         
-        // Render right pane
+        // Render right pane, the input growing with its text
+        let input_rows = self.composer.height(chunks[1].width.saturating_sub(2)).clamp(1, MAX_INPUT_ROWS);
         let right_chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
                 Constraint::Min(0),
-                Constraint::Length(3),
+                Constraint::Length(input_rows + 2),
             ])
             .split(chunks[1]);
         
-        self.render_messages(frame, right_chunks[0]);
+        if self.search.is_some() {
+            self.render_search(frame, right_chunks[0]);
+        } else {
+            self.render_messages(frame, right_chunks[0]);
+        }
         self.render_input(frame, right_chunks[1]);
         
         // Render status bar
@@ -838,8 +2433,12 @@ impl App {
     }
     
     fn render_status_bar(&self, frame: &mut Frame, area: Rect) {
-        let status = Paragraph::new(self.status_message.as_str())
-            .style(Style::default().fg(self.theme.system));
+        let status = match &self.theme_error {
+            Some(error) => Paragraph::new(error.as_str())
+                .style(Style::default().fg(self.theme.highlight)),
+            None => Paragraph::new(self.status_message.as_str())
+                .style(Style::default().fg(self.theme.system)),
+        };
         frame.render_widget(status, area);
     }
     
@@ -847,21 +2446,10 @@ impl App {
         // Store the area for mouse click detection
         self.chat_list_area = area;
 
-        // Filter chats based on current view
-        let filtered_chats: Vec<&Chat> = match self.chat_list_view {
-            ChatListView::Normal => {
-                // Show non-archived chats
-                self.chats.iter()
-                    .filter(|c| !c.archived)
-                    .collect()
-            },
-            ChatListView::Archived => {
-                // Show only archived chats
-                self.chats.iter()
-                    .filter(|c| c.archived)
-                    .collect()
-            }
-        };
+        // Filter chats based on current view (or the filter prompt)
+        let filtered_chats: Vec<&Chat> = self.visible_chat_indices().into_iter()
+            .map(|i| &self.chats[i])
+            .collect();
 
         // Calculate total items (chats + "Archived Messages")
         let total_items = filtered_chats.len() + 1;
@@ -891,6 +2479,7 @@ impl App {
 
         // Build items to display
         let mut items: Vec<ListItem> = Vec::new();
+        let mut avatars: Vec<(String, u16)> = Vec::new();  // chat_id, row in the window
 
         for i in self.chat_list_scroll..end_index {
             // First item is always "Archived Messages"
@@ -907,8 +2496,10 @@ impl App {
                 // Check if there are any archived chats
                 let archived_count = self.chats.iter().filter(|c| c.archived).count();
                 
-                // Change indicator based on current view
-                let content = if self.chat_list_view == ChatListView::Archived {
+                // Change indicator based on current view, or show the filter prompt
+                let content = if let Some(filter) = &self.chat_filter {
+                    format!("🔍 {}▏", filter.query)
+                } else if self.chat_list_view == ChatListView::Archived {
                     if archived_count > 0 {
                         format!("📂 Archived Messages ({}) - Viewing", archived_count)
                     } else {
@@ -945,9 +2536,40 @@ impl App {
                         Style::default()
                     };
 
-                    let content = format!("{}{}", name, unread);
-                    let line = Line::from(Span::styled(content, text_style));
-                    items.push(ListItem::new(line));
+                    let mut spans = Vec::new();
+                    if self.show_avatars {
+                        // The image goes over the blank cells once the list is drawn
+                        if self.graphics.has_avatar(&chat.id) {
+                            avatars.push((chat.id.clone(), items.len() as u16));
+                            spans.push(Span::raw("  "));
+                        } else {
+                            spans.push(Span::styled(
+                                initials(name),
+                                Style::default().fg(Color::Black).bg(badge_color(&chat.id)),
+                            ));
+                        }
+                        spans.push(Span::styled(" ", text_style));
+                    }
+                    // Matched letters stand out while filtering
+                    let matched = self.chat_filter.as_ref()
+                        .and_then(|filter| fuzzy::find(&filter.query, name))
+                        .map(|(_, positions)| positions)
+                        .unwrap_or_default();
+                    if matched.is_empty() {
+                        spans.push(Span::styled(name.clone(), text_style));
+                    } else {
+                        let match_style = text_style.fg(self.theme.highlight).add_modifier(Modifier::BOLD);
+                        for (i, c) in name.chars().enumerate() {
+                            let style = if matched.contains(&i) { match_style } else { text_style };
+                            spans.push(Span::styled(c.to_string(), style));
+                        }
+                    }
+                    spans.push(Span::styled(unread, text_style));
+                    if let Some(draft) = self.drafts.get(&chat.id) {
+                        spans.push(Span::styled(" Draft: ", text_style.fg(self.theme.highlight)));
+                        spans.push(Span::styled(format::snippet(draft, 24), text_style.fg(self.theme.system)));
+                    }
+                    items.push(ListItem::new(Line::from(spans)));
                 }
             }
         }
@@ -958,10 +2580,10 @@ impl App {
             self.theme.border
         };
 
-        // Always use "Chats" title
+        let title = if self.chat_filter.is_some() { " Filter (Enter: open, Esc: back) " } else { " Chats " };
         let list = List::new(items)
             .block(Block::default()
-                .title(" Chats ")
+                .title(title)
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded)
                 .border_style(Style::default().fg(border_color)))
@@ -972,96 +2594,476 @@ impl App {
         display_state.select(selected_in_window);
 
         frame.render_stateful_widget(list, area, &mut display_state);
+
+        // Items shift right to make room for the highlight symbol while one is selected
+        let indent = if selected_in_window.is_some() { 2 } else { 0 };
+        for (chat_id, row) in avatars {
+            let avatar = Rect { x: area.x + 1 + indent, y: area.y + 1 + row, width: 2, height: 1 };
+            if avatar.right() < area.right() {
+                self.graphics.draw(frame.buffer_mut(), ImageKey::Avatar(chat_id), avatar);
+            }
+        }
     }
     
+    fn render_search(&mut self, frame: &mut Frame, area: Rect) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+
+        let block = Block::default()
+            .title(" Search messages (Enter: open, Ctrl+S: search server, Esc: close) ")
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(self.theme.border_focused));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),  // Query
+                Constraint::Length(1),  // Result count and server status
+                Constraint::Min(0),
+            ])
+            .split(inner);
+
+        frame.render_widget(
+            Paragraph::new(format!("🔍 {}▏", search.query)).style(Style::default().fg(self.theme.highlight)),
+            chunks[0],
+        );
+
+        let mut status = match search.results.len() {
+            0 if search.query.trim().is_empty() => "Type to search cached messages".to_string(),
+            1 => "1 message".to_string(),
+            n => format!("{} messages", n),
+        };
+        if let Some(server) = &search.server_status {
+            status = format!("{} - {}", status, server);
+        }
+        frame.render_widget(Paragraph::new(status).style(Style::default().fg(self.theme.system)), chunks[1]);
+
+        let query = search.query.trim();
+        let items: Vec<ListItem> = search.results.iter()
+            .map(|msg| {
+                let chat = self.chats.iter()
+                    .find(|c| c.id == msg.chat_id)
+                    .map(|c| c.name.as_str())
+                    .unwrap_or("Unknown chat");
+                let sender = if msg.from_me {
+                    Span::styled("Me", Style::default().fg(self.theme.me))
+                } else {
+                    Span::styled(msg.sender.clone().unwrap_or_else(|| chat.to_string()), Style::default().fg(self.theme.other))
+                };
+                let header = Line::from(vec![
+                    Span::styled(chat.to_string(), Style::default().add_modifier(Modifier::BOLD)),
+                    Span::styled(" · ", Style::default().fg(self.theme.system)),
+                    sender,
+                    Span::styled(format!(" · {}", self.clock.date_time(msg.timestamp)), Style::default().fg(self.theme.system)),
+                ]);
+
+                let mut body = vec![Span::raw("    ")];
+                body.extend(search_snippet(&msg.body, query, Style::default().fg(self.theme.highlight).add_modifier(Modifier::BOLD)));
+                ListItem::new(vec![header, Line::from(body)])
+            })
+            .collect();
+
+        let list = List::new(items)
+            .highlight_symbol("► ")
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, chunks[2], &mut search.list_state);
+    }
+
     fn render_messages(&mut self, frame: &mut Frame, area: Rect) {
         // Store area for mouse detection
         self.message_view_area = area;
         let title = if let Some(chat_id) = &self.current_chat_id {
             self.chats.iter()
                 .find(|c| c.id == *chat_id)
-                .map(|c| format!(" {} ", c.name))
+                .map(|c| if self.selected_message_id.is_some() {
+                    format!(" {} (Enter: actions, Space: mark, Esc: done) ", c.name)
+                } else {
+                    format!(" {} ", c.name)
+                })
                 .unwrap_or_else(|| " Messages ".to_string())
         } else {
             " Messages ".to_string()
         };
-        
-        let messages_text = if let Some(chat_id) = &self.current_chat_id {
-        if let Some(messages) = self.messages.get(chat_id) {
-            let mut text = Text::default();
 
-            for msg in messages {
-                let sender = if msg.from_me {
-                    Span::styled("Me", Style::default().fg(self.theme.me))
-                } else {
-                    Span::styled(
-                        msg.sender.as_deref().unwrap_or("User"),
-                        Style::default().fg(self.theme.other)
-                    )
-                };
+        let inner_width = area.width.saturating_sub(2);
+        let available_height = area.height.saturating_sub(2) as usize;
+
+        // Everything in the view, oldest first: messages reuse their rows from the last frame
+        let mut previous = std::mem::take(&mut self.wrapped_messages);
+        let mut wrapped = HashMap::new();
+        let mut blocks: Vec<ViewBlock> = Vec::new();
+        let placeholder = match self.current_chat_id.as_ref().map(|id| (id, self.messages.get(id))) {
+            None => Some("Select a chat to view messages"),
+            Some((_, None)) => Some("Loading messages..."),
+            Some((chat_id, Some(messages))) => {
+                let mut last_day = None;
+
+                for msg in messages {
+                    // Separator whenever the day changes
+                    if let Some(day) = self.clock.day(msg.timestamp).filter(|day| last_day != Some(*day)) {
+                        blocks.push(ViewBlock::Rows(wrap::wrap_line(&self.day_separator(day), inner_width)));
+                        last_day = Some(day);
+                    }
 
-                let time = chrono::DateTime::from_timestamp(msg.timestamp, 0)
-                    .map(|dt| dt.with_timezone(&chrono::Local).format("%H:%M").to_string())
-                    .unwrap_or_default();
+                    let lines = self.message_lines(msg);
+                    let message = previous.remove(&msg.id)
+                        .filter(|m: &WrappedMessage| m.width == inner_width && m.lines == lines)
+                        .unwrap_or_else(|| WrappedMessage::new(lines, inner_width));
 
-                let body = if msg.has_media {
-                    format!("[Media: {}]", msg.media_type.as_deref().unwrap_or("unknown"))
-                } else {
-                    msg.body.clone()
-                };
+                    // Blank rows under the message, between text and reactions, for the image
+                    let preview = if has_preview(msg) {
+                        self.graphics.preview_size(&msg.id, inner_width.saturating_sub(PREVIEW_INDENT))
+                    } else {
+                        None
+                    };
+                    let preview = preview.map(|(cols, rows)| {
+                        let at = message.lines.len() - usize::from(!msg.reactions.is_empty());
+                        (message.line_rows[..at].iter().sum(), cols, rows)
+                    });
+
+                    blocks.push(ViewBlock::Message {
+                        id: msg.id.clone(),
+                        height: message.rows.len() + preview.map_or(0, |(_, _, rows)| rows as usize),
+                        preview,
+                    });
+                    wrapped.insert(msg.id.clone(), message);
+                }
 
-                // Create a single line with all components - Paragraph will wrap it
-                let message_line = Line::from(vec![
-                    Span::styled(time, Style::default().fg(self.theme.system)),
-                    Span::raw(" "),
-                    sender,
-                    Span::raw(": "),
-                    Span::raw(body),
-                ]);
+                // Queued messages go last, marked until the service confirms them
+                for entry in self.outbox.iter().filter(|e| e.chat_id == *chat_id) {
+                    if let Some(day) = self.clock.day(entry.created_at).filter(|day| last_day != Some(*day)) {
+                        blocks.push(ViewBlock::Rows(wrap::wrap_line(&self.day_separator(day), inner_width)));
+                        last_day = Some(day);
+                    }
+
+                    let status = match entry.state {
+                        OutboxState::Pending | OutboxState::Sending => self.ack_ticks(AckStatus::Pending),
+                        OutboxState::Failed => Span::styled(
+                            format!(" ⚠ {} (Ctrl+R: retry, Ctrl+X: discard)", entry.error.as_deref().unwrap_or("failed")),
+                            Style::default().fg(self.theme.highlight),
+                        ),
+                    };
 
-                text.lines.push(message_line);
+                    let mut spans = vec![
+                        Span::styled(self.clock.time(entry.created_at), Style::default().fg(self.theme.system)),
+                        Span::raw(" "),
+                        Span::styled("Me", Style::default().fg(self.theme.me)),
+                        Span::raw(": "),
+                    ];
+                    spans.extend(format::spans(&entry.text, Style::default().fg(self.theme.system), Style::default().fg(self.theme.highlight)));
+                    spans.push(status);
+                    blocks.push(ViewBlock::Rows(wrap::wrap_line(&Line::from(spans), inner_width)));
+                }
+                None
+            }
+        };
+        // Messages no longer shown (another chat, deleted) are forgotten
+        self.wrapped_messages = wrapped;
+
+        // Wrapped height of everything, and the rows of the selected message
+        let num_lines: usize = blocks.iter().map(ViewBlock::height).sum();
+        let mut selected_rows: Option<(usize, usize)> = None;
+        let mut row = 0;
+        for block in &blocks {
+            if let ViewBlock::Message { id, height, .. } = block {
+                if self.selected_message_id.as_ref() == Some(id) {
+                    selected_rows = Some((row, row + height));
+                }
             }
+            row += block.height();
+        }
 
-            text
-        } else {
-            Text::from("Loading messages...")
+        // Calculate scroll offset
+        // Inverted: scroll=0 means "at bottom" (newest messages)
+        // Increasing message_scroll means scrolling UP (shows older messages)
+        let max_scroll = num_lines.saturating_sub(available_height);
+        self.message_max_scroll = max_scroll as u16;
+
+        // Bring the selected message into view after the cursor moved
+        if self.follow_selection {
+            self.follow_selection = false;
+            if let Some((top, bottom)) = selected_rows {
+                let current_top = max_scroll.saturating_sub(self.message_scroll as usize);
+                let new_top = if top < current_top {
+                    top
+                } else if bottom > current_top + available_height {
+                    bottom.saturating_sub(available_height).min(top)
+                } else {
+                    current_top
+                };
+                self.message_scroll = (max_scroll.saturating_sub(new_top)) as u16;
+            }
         }
-    } else {
-        Text::from("Select a chat to view messages")
-    };
-    
-    // Calculate scroll offset
-    // Inverted: scroll=0 means "at bottom" (newest messages)
-    // Increasing message_scroll means scrolling UP (shows older messages)
-    let num_lines = messages_text.lines.len();
-    let available_height = area.height.saturating_sub(2) as usize;
-    
-    // Calculate scroll offset from bottom
-    let scroll_offset = if num_lines > available_height {
-        let max_scroll = (num_lines - available_height) as u16;
+
         // Invert: subtract message_scroll from max to scroll from bottom
-        max_scroll.saturating_sub(self.message_scroll)
-    } else {
-        0  // Not enough messages to scroll
-    };
-        
+        let top = max_scroll.saturating_sub(self.message_scroll as usize);
+        let shown = top..top + available_height;
+
+        // Only the rows in view are built
+        let mut visible: Vec<Line<'static>> = Vec::new();
+        let mut previews: Vec<(String, usize, u16, u16)> = Vec::new();  // message_id, first row, cols, rows
+        let mut row = 0;
+        for block in &blocks {
+            let height = block.height();
+            if row < shown.end && row + height > shown.start {
+                let rows = match block {
+                    ViewBlock::Rows(rows) => rows.clone(),
+                    ViewBlock::Message { id, preview, .. } => {
+                        let mut rows = self.wrapped_messages[id].rows.clone();
+                        if let Some((at, cols, preview_rows)) = *preview {
+                            previews.push((id.clone(), row + at, cols, preview_rows));
+                            rows.splice(at..at, (0..preview_rows).map(|_| Line::default()));
+                        }
+                        if self.selected_message_id.as_ref() == Some(id) {
+                            let selected_style = Style::default()
+                                .bg(self.theme.primary)
+                                .add_modifier(Modifier::BOLD);
+                            for line in &mut rows {
+                                line.style = line.style.patch(selected_style);
+                            }
+                        }
+                        rows
+                    }
+                };
+                visible.extend(rows.into_iter()
+                    .enumerate()
+                    .filter(|(i, _)| shown.contains(&(row + i)))
+                    .map(|(_, line)| line));
+            }
+            row += height;
+        }
+
         // Border color based on focus
         let border_color = if self.focused == FocusedWidget::MessageView {
             self.theme.border_focused
         } else {
             self.theme.border
         };
-        
-        let paragraph = Paragraph::new(messages_text)
+
+        let paragraph = Paragraph::new(placeholder.map_or(Text::from(visible), Text::from))
             .block(Block::default()
                 .title(title)
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded)
-                .border_style(Style::default().fg(border_color)))
-            .wrap(Wrap { trim: false })
-            .scroll((scroll_offset, 0));
-        
+                .border_style(Style::default().fg(border_color)));
+
         frame.render_widget(paragraph, area);
+
+        // Only fully visible previews are drawn, and none under a popup
+        let popup = self.message_popup().map(|popup| (popup.area(area), popup));
+        let picker = self.forward_picker.is_some().then(|| ForwardPicker::area(area));
+        for (message_id, row, cols, rows) in previews {
+            if row < top || row + rows as usize > top + available_height {
+                continue;
+            }
+            let preview = Rect {
+                x: area.x + 1 + PREVIEW_INDENT,
+                y: area.y + 1 + (row - top) as u16,
+                width: cols,
+                height: rows,
+            };
+            let covered = popup.as_ref().is_some_and(|(rect, _)| rect.intersects(preview))
+                || picker.is_some_and(|rect| rect.intersects(preview));
+            if !covered {
+                self.graphics.draw(frame.buffer_mut(), ImageKey::Preview(message_id), preview);
+            }
+        }
+
+        if let Some((rect, popup)) = popup {
+            popup.render(rect, frame.buffer_mut(), &self.theme);
+        }
+        if let (Some(rect), Some(picker)) = (picker, &mut self.forward_picker) {
+            picker.render(rect, frame.buffer_mut(), &self.chats, &self.theme);
+        }
+    }
+
+    /// The popup open over the message view, if any
+    fn message_popup(&self) -> Option<MessagePopup> {
+        let key_style = Style::default().fg(self.theme.primary).add_modifier(Modifier::BOLD);
+
+        if let Some(index) = self.reaction_picker {
+            return Some(MessagePopup::new(" React (Enter: send, Esc: cancel) ", vec![self.reaction_picker_line(index)]));
+        }
+
+        if self.delete_prompt {
+            let can_revoke = self.selected_message().is_some_and(|m| m.from_me);
+            let mut spans = vec![
+                Span::styled(" m", key_style),
+                Span::raw(": for me "),
+            ];
+            if can_revoke {
+                spans.push(Span::styled(" e", key_style));
+                spans.push(Span::raw(": for everyone "));
+            }
+            return Some(MessagePopup::new(" Delete message? (Esc: cancel) ", vec![Line::from(spans)]));
+        }
+
+        if self.message_details {
+            let msg = self.selected_message()?;
+            let details = MessageDetails {
+                message: msg,
+                chat: self.chats.iter().find(|c| c.id == msg.chat_id).map(|c| c.name.as_str()).unwrap_or_default(),
+                sent: self.clock.date_time(msg.timestamp),
+                saved_at: self.downloaded.get(&msg.id).map(PathBuf::as_path),
+                reactions: (!msg.reactions.is_empty()).then(|| self.reaction_line(&msg.reactions)),
+            };
+            return Some(MessagePopup::new(MessageDetails::TITLE, details.lines(&self.theme)));
+        }
+
+        let menu = self.message_menu.as_ref()?;
+        Some(MessagePopup::new(menu.title(), menu.lines(&self.theme)))
+    }
+
+    /// The quick reactions, the highlighted one marked
+    fn reaction_picker_line(&self, index: usize) -> Line<'static> {
+        let mut spans = Vec::new();
+        for (i, emoji) in REACTION_EMOJIS.iter().enumerate() {
+            let style = if i == index {
+                Style::default().bg(self.theme.primary).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            spans.push(Span::raw(" "));
+            spans.push(Span::styled(format!("{} {}", i + 1, emoji), style));
+        }
+        spans.push(Span::raw(" "));
+        Line::from(spans)
+    }
+
+    /// Centered "── Today ──" line above the first message of each day
+    fn day_separator(&self, day: chrono::NaiveDate) -> Line<'static> {
+        Line::styled(
+            format!("── {} ──", self.clock.day_label(day)),
+            Style::default().fg(self.theme.system),
+        ).centered()
+    }
+
+    /// Lines for a single message: quoted snippet (if a reply), then the message itself
+    fn message_lines(&self, msg: &Message) -> Vec<Line<'static>> {
+        let mut lines = Vec::new();
+
+        if msg.forwarded && !msg.from_me {
+            lines.push(Line::styled(
+                "      ↪ Forwarded",
+                Style::default().fg(self.theme.system).add_modifier(Modifier::ITALIC),
+            ));
+        }
+
+        if let Some(quoted) = &msg.quoted {
+            let author = if quoted.from_me {
+                "Me".to_string()
+            } else {
+                quoted.sender.clone().unwrap_or_else(|| "User".to_string())
+            };
+            lines.push(Line::from(vec![
+                Span::styled("      ┃ ", Style::default().fg(self.theme.primary)),
+                Span::styled(format!("{}: ", author), Style::default().fg(self.theme.other)),
+                Span::styled(format::snippet(&quoted.body, 60), Style::default().fg(self.theme.system)),
+            ]));
+        }
+
+        let sender = if msg.from_me {
+            Span::styled("Me", Style::default().fg(self.theme.me))
+        } else {
+            Span::styled(
+                msg.sender.clone().unwrap_or_else(|| "User".to_string()),
+                Style::default().fg(self.theme.other)
+            )
+        };
+
+        let code = Style::default().fg(self.theme.highlight);
+        let body = if msg.deleted {
+            vec![Span::styled(
+                "🚫 This message was deleted",
+                Style::default().fg(self.theme.system).add_modifier(Modifier::ITALIC),
+            )]
+        } else if msg.has_media {
+            let mut label = format!("[Media: {}", msg.media_type.as_deref().unwrap_or("unknown"));
+            if let Some((received, total)) = self.downloads.get(&msg.id) {
+                label.push_str(&format!(" ⬇ {}%", received * 100 / total.max(&1)));
+            } else if let Some(name) = self.downloaded.get(&msg.id).and_then(|p| p.file_name()) {
+                label.push_str(&format!(" ✔ {}", name.to_string_lossy()));
+            }
+            label.push(']');
+
+            if msg.body.is_empty() {
+                vec![Span::raw(label)]
+            } else {
+                let mut spans = vec![Span::raw(format!("{} ", label))];
+                spans.extend(format::spans(&msg.body, Style::default(), code));
+                spans
+            }
+        } else {
+            format::spans(&msg.body, Style::default(), code)
+        };
+
+        // Create a single line with all components - Paragraph will wrap it
+        let mut spans = vec![
+            Span::styled(self.clock.time(msg.timestamp), Style::default().fg(self.theme.system)),
+            Span::raw(" "),
+            sender,
+            Span::raw(": "),
+        ];
+        spans.extend(body);
+        if self.marked_messages.contains(&msg.id) {
+            spans.insert(0, Span::styled("● ", Style::default().fg(self.theme.highlight).add_modifier(Modifier::BOLD)));
+        }
+        if msg.edited && !msg.deleted {
+            spans.push(Span::styled(" (edited)", Style::default().fg(self.theme.system)));
+        }
+        if msg.from_me && !msg.deleted {
+            spans.push(self.ack_ticks(msg.ack));
+        }
+        lines.push(Line::from(spans));
+
+        if !msg.reactions.is_empty() {
+            lines.push(self.reaction_line(&msg.reactions));
+        }
+
+        lines
+    }
+
+    /// Reactions aggregated per emoji in order of first appearance; ours is highlighted
+    fn reaction_line(&self, reactions: &[Reaction]) -> Line<'static> {
+        let mut counts: Vec<(&str, usize, bool)> = Vec::new();
+        for reaction in reactions {
+            match counts.iter_mut().find(|(emoji, _, _)| *emoji == reaction.emoji) {
+                Some((_, count, mine)) => {
+                    *count += 1;
+                    *mine |= reaction.from_me;
+                }
+                None => counts.push((&reaction.emoji, 1, reaction.from_me)),
+            }
+        }
+
+        let mut spans = vec![Span::raw("      ")];
+        for (emoji, count, mine) in counts {
+            let style = if mine {
+                Style::default().fg(self.theme.primary).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(self.theme.system)
+            };
+            spans.push(Span::styled(format!("{} {}", emoji, count), style));
+            spans.push(Span::raw("  "));
+        }
+        spans.pop();
+
+        Line::from(spans)
+    }
+    
+    /// Delivery ticks shown after our own messages
+    fn ack_ticks(&self, ack: AckStatus) -> Span<'static> {
+        match ack {
+            AckStatus::Error => Span::styled(" ⚠", Style::default().fg(self.theme.highlight)),
+            AckStatus::Pending => Span::styled(" 🕓", Style::default().fg(self.theme.system)),
+            AckStatus::Sent => Span::styled(" ✓", Style::default().fg(self.theme.system)),
+            AckStatus::Delivered => Span::styled(" ✓✓", Style::default().fg(self.theme.system)),
+            AckStatus::Read | AckStatus::Played => Span::styled(" ✓✓", Style::default().fg(self.theme.primary)),
+        }
     }
     
     fn render_input(&mut self, frame: &mut Frame, area: Rect) {
@@ -1073,13 +3075,110 @@ impl App {
             self.theme.border
         };
         
-        let input = Paragraph::new(self.input_buffer.as_str())
-            .block(Block::default()
-                .title(" Type message (Enter: send, Esc: clear, Tab: change focus) ")
-                .borders(Borders::ALL)
-                .border_type(BorderType::Rounded)
-                .border_style(Style::default().fg(border_color)));
+        let title = match (&self.editing, &self.attachment, &self.reply_to) {
+            (Some(_), _, _) => " Editing message (Enter: save, Esc: cancel) ".to_string(),
+            (None, Some(upload), _) => {
+                format!(" 📎 {} ({}) - caption (Enter: send, Esc: remove) ", upload.filename, format_size(upload.size))
+            }
+            (None, None, Some(msg)) => {
+                let author = if msg.from_me { "yourself" } else { msg.sender.as_deref().unwrap_or("User") };
+                format!(" Replying to {}: {} (Esc: cancel) ", author, format::snippet(&msg.body, 40))
+            }
+            (None, None, None) => " Type message (Enter: send, Alt+Enter: new line, Ctrl+E: editor, Esc: clear) ".to_string(),
+        };
+
+        let block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(border_color));
+        let inner = block.inner(area);
         
-        frame.render_widget(input, area);
+        frame.render_widget(block, area);
+        self.composer.render(inner, frame.buffer_mut(), self.focused == FocusedWidget::Input, Style::default().fg(self.theme.highlight));
+    }
+}
+
+/// AvatarLoaded event for a profile picture, logging why it can't be shown
+fn decode_avatar(chat_id: &str, data: Option<Vec<u8>>) -> WhatsAppEvent {
+    let avatar = data.and_then(|data| match graphics::load_avatar(&data) {
+        Ok(image) => Some(Arc::new(image)),
+        Err(e) => {
+            log::warn!("No avatar for {}: {:#}", chat_id, e);
+            None
+        }
+    });
+    WhatsAppEvent::AvatarLoaded(chat_id.to_string(), avatar)
+}
+
+/// Two-letter badge for a chat without a picture: "AB" from "Alice Brown", "AL" from "Alice"
+fn initials(name: &str) -> String {
+    let words: Vec<Vec<char>> = name.split_whitespace()
+        .map(|word| word.chars().filter(|c| c.is_alphanumeric()).collect::<Vec<_>>())
+        .filter(|word| !word.is_empty())
+        .collect();
+    let letters: String = match words.as_slice() {
+        [] => "?".to_string(),
+        [word] => word.iter().take(2).collect(),
+        [first, second, ..] => [first[0], second[0]].into_iter().collect(),
+    };
+    format!("{:<2}", letters.to_uppercase())
+}
+
+/// Badge color for a chat, the same on every launch
+fn badge_color(chat_id: &str) -> Color {
+    let hash = chat_id.bytes().fold(0usize, |hash, b| hash.wrapping_mul(31).wrapping_add(b as usize));
+    BADGE_COLORS[hash % BADGE_COLORS.len()]
+}
+
+/// Add found messages to a search, without duplicates, newest first
+fn add_search_results(search: &mut Search, found: Vec<Message>) {
+    let mut seen: HashSet<String> = search.results.iter().map(|m| m.id.clone()).collect();
+    search.results.extend(found.into_iter().filter(|m| seen.insert(m.id.clone())));
+    search.results.sort_by_key(|m| std::cmp::Reverse(m.timestamp));
+    search.results.truncate(SEARCH_LIMIT);
+
+    let selected = search.list_state.selected().unwrap_or(0);
+    search.list_state.select((!search.results.is_empty()).then(|| selected.min(search.results.len() - 1)));
+}
+
+/// Char position of the first match of `query` in `text`, ignoring case
+fn find_ignore_case(text: &str, query: &str) -> Option<usize> {
+    let text: Vec<char> = text.chars().map(fuzzy::fold_case).collect();
+    let query: Vec<char> = query.chars().map(fuzzy::fold_case).collect();
+    if query.is_empty() || query.len() > text.len() {
+        return None;
+    }
+    (0..=text.len() - query.len()).find(|&i| text[i..].starts_with(&query))
+}
+
+/// A message body on one line around the first match of `query`, the match highlighted
+fn search_snippet(body: &str, query: &str, match_style: Style) -> Vec<Span<'static>> {
+    let chars: Vec<char> = body.chars().map(|c| if c.is_whitespace() { ' ' } else { c }).collect();
+    let Some(start) = find_ignore_case(body, query) else {
+        return vec![Span::raw(format::snippet(body, 80))];
+    };
+    let end = start + query.chars().count();
+
+    let from = start.saturating_sub(SNIPPET_LEAD);
+    let lead = if from > 0 { "…" } else { "" };
+    vec![
+        Span::raw(format!("{}{}", lead, chars[from..start].iter().collect::<String>())),
+        Span::styled(chars[start..end].iter().collect::<String>(), match_style),
+        Span::raw(chars[end..].iter().take(200).collect::<String>()),
+    ]
+}
+
+/// Images and stickers get an inline preview
+fn has_preview(msg: &Message) -> bool {
+    msg.has_media && !msg.deleted && matches!(msg.media_type.as_deref(), Some("image" | "sticker"))
+}
+
+/// Human readable byte count
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1_048_575 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1024.0 / 1024.0),
     }
 }
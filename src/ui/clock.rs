@@ -0,0 +1,156 @@
+use std::fmt::{self, Write};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+
+use crate::config::{self, UiConfig};
+
+/// Messages younger than this show "5m ago" instead of a clock time
+const RELATIVE_SECS: i64 = 60 * 60;
+
+#[derive(Debug, Clone, Copy)]
+enum Zone {
+    Local,
+    Named(Tz),
+}
+
+/// Formats message times and day separators in the configured timezone
+pub struct Clock {
+    zone: Zone,
+    time_format: String,
+    date_format: String,
+    relative: bool,
+}
+
+impl Clock {
+    pub fn new(config: &UiConfig) -> Self {
+        let zone = match config.timezone.as_str() {
+            "local" => Zone::Local,
+            name => match name.parse::<Tz>() {
+                Ok(tz) => Zone::Named(tz),
+                Err(e) => {
+                    log::warn!("Unknown timezone '{}' ({}), using local time", name, e);
+                    Zone::Local
+                }
+            },
+        };
+
+        Self {
+            zone,
+            time_format: checked_format(&config.time_format, config::default_time_format, |format, out| {
+                write!(out, "{}", NaiveDateTime::default().format(format))
+            }),
+            date_format: checked_format(&config.date_format, config::default_date_format, |format, out| {
+                write!(out, "{}", NaiveDate::default().format(format))
+            }),
+            relative: config.relative_timestamps,
+        }
+    }
+
+    /// Time of a message: "5m ago" if recent and enabled, else e.g. "14:05"
+    pub fn time(&self, timestamp: i64) -> String {
+        if self.relative {
+            let age = Utc::now().timestamp() - timestamp;
+            if (0..60).contains(&age) {
+                return "now".to_string();
+            }
+            if (60..RELATIVE_SECS).contains(&age) {
+                return format!("{}m ago", age / 60);
+            }
+        }
+
+        self.local(timestamp)
+            .map(|dt| dt.format(&self.time_format).to_string())
+            .unwrap_or_default()
+    }
+
+    /// Day and clock time, e.g. "Yesterday 14:05", never relative
+    pub fn date_time(&self, timestamp: i64) -> String {
+        self.local(timestamp)
+            .map(|dt| format!("{} {}", self.day_label(dt.date()), dt.format(&self.time_format)))
+            .unwrap_or_default()
+    }
+
+    /// Calendar day of a timestamp in the configured timezone
+    pub fn day(&self, timestamp: i64) -> Option<NaiveDate> {
+        self.local(timestamp).map(|dt| dt.date())
+    }
+
+    /// "Today", "Yesterday" or the formatted date
+    pub fn day_label(&self, day: NaiveDate) -> String {
+        let today = self.day(Utc::now().timestamp());
+        if Some(day) == today {
+            "Today".to_string()
+        } else if Some(day) == today.and_then(|t| t.pred_opt()) {
+            "Yesterday".to_string()
+        } else {
+            day.format(&self.date_format).to_string()
+        }
+    }
+
+    fn local(&self, timestamp: i64) -> Option<NaiveDateTime> {
+        let utc = DateTime::from_timestamp(timestamp, 0)?;
+        Some(match self.zone {
+            Zone::Local => utc.with_timezone(&chrono::Local).naive_local(),
+            Zone::Named(tz) => utc.with_timezone(&tz).naive_local(),
+        })
+    }
+}
+
+/// `format` if `sample` can format a value with it, else the default
+///
+/// A format chrono can't parse, or one asking for a field the value doesn't have (a
+/// zone on a time, an hour on a date), would panic on every frame.
+fn checked_format(
+    format: &str,
+    default: fn() -> String,
+    sample: impl Fn(&str, &mut String) -> fmt::Result,
+) -> String {
+    if sample(format, &mut String::new()).is_err() {
+        log::warn!("Invalid date/time format '{}', using the default", format);
+        return default();
+    }
+    format.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn clock(time_format: &str, date_format: &str) -> Clock {
+        let mut config = Config::default().ui;
+        config.relative_timestamps = false;
+        config.timezone = "UTC".to_string();
+        config.time_format = time_format.to_string();
+        config.date_format = date_format.to_string();
+        Clock::new(&config)
+    }
+
+    #[test]
+    fn time_format_needing_a_zone_falls_back() {
+        let clock = clock("%H:%M %Z", "%d %b");
+        assert_eq!(clock.time_format, config::default_time_format());
+        assert_eq!(clock.time(0), "00:00");
+    }
+
+    #[test]
+    fn date_format_needing_a_time_falls_back() {
+        let clock = clock("%H:%M", "%d %b %H:%M");
+        assert_eq!(clock.date_format, config::default_date_format());
+        let day = NaiveDate::from_ymd_opt(2020, 3, 1).unwrap();
+        assert_eq!(clock.day_label(day), day.format(&config::default_date_format()).to_string());
+    }
+
+    #[test]
+    fn unparsable_format_falls_back() {
+        assert_eq!(clock("%Q", "%d %b").time_format, config::default_time_format());
+    }
+
+    #[test]
+    fn valid_formats_are_kept() {
+        let clock = clock("%I:%M %p", "%Y-%m-%d");
+        assert_eq!(clock.time(0), "12:00 AM");
+        assert_eq!(clock.day_label(NaiveDate::from_ymd_opt(2020, 3, 1).unwrap()), "2020-03-01");
+    }
+}
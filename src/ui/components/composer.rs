@@ -0,0 +1,168 @@
+use crossterm::event::KeyEvent;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+};
+use tui_textarea::{CursorMove, TextArea};
+use unicode_width::UnicodeWidthChar;
+
+use crate::ui::format;
+
+/// Spaces a pasted tab turns into
+const TAB_WIDTH: usize = 4;
+
+/// Message input: tui-textarea does the editing, drawing wraps long lines
+///
+/// tui-textarea scrolls long lines sideways, which hides most of a long
+/// message, so the text is drawn here, broken at the pane width and with
+/// its WhatsApp formatting shown as it will be sent.
+pub struct Composer {
+    textarea: TextArea<'static>,
+    scroll: u16,  // First visible row of the wrapped text
+}
+
+/// One screen row of wrapped text: chars `start..end` of a line
+struct Row {
+    line: usize,
+    start: usize,
+    end: usize,
+}
+
+impl Composer {
+    pub fn new() -> Self {
+        Self {
+            textarea: TextArea::default(),
+            scroll: 0,
+        }
+    }
+
+    /// Edit with tui-textarea's keys: arrows, Home/End, Ctrl+W, Alt+Backspace, Ctrl+U...
+    pub fn input(&mut self, key: KeyEvent) -> bool {
+        self.textarea.input(key)
+    }
+
+    pub fn insert_newline(&mut self) {
+        self.textarea.insert_newline();
+    }
+
+    /// Insert pasted text at the cursor, whatever its line endings
+    pub fn paste(&mut self, text: &str) {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n").replace('\t', &" ".repeat(TAB_WIDTH));
+        self.textarea.insert_str(text);
+    }
+
+    pub fn text(&self) -> String {
+        self.textarea.lines().join("\n")
+    }
+
+    /// Replace the text, with the cursor at the end
+    pub fn set_text(&mut self, text: &str) {
+        self.textarea = TextArea::new(text.split('\n').map(String::from).collect());
+        self.textarea.move_cursor(CursorMove::Bottom);
+        self.textarea.move_cursor(CursorMove::End);
+        self.scroll = 0;
+    }
+
+    pub fn clear(&mut self) {
+        self.set_text("");
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textarea.is_empty()
+    }
+
+    /// Rows the text takes up when wrapped to `width`
+    pub fn height(&self, width: u16) -> u16 {
+        self.wrap(width).len() as u16
+    }
+
+    /// Draw the visible rows, scrolled so the cursor stays in view, code in `code`'s colors
+    pub fn render(&mut self, area: Rect, buf: &mut Buffer, show_cursor: bool, code: Style) {
+        if area.is_empty() {
+            return;
+        }
+
+        let rows = self.wrap(area.width);
+        let (cursor_row, cursor_col) = self.cursor_position(&rows);
+        let max_scroll = (rows.len() as u16).saturating_sub(area.height);
+        self.scroll = self.scroll
+            .clamp(cursor_row.saturating_sub(area.height - 1), cursor_row)
+            .min(max_scroll);
+
+        // Formatting can span lines (code blocks), so it's worked out on the whole text
+        let lines = self.textarea.lines();
+        let formats = format::parse(&self.text());
+        let mut line_starts = Vec::with_capacity(lines.len());
+        let mut offset = 0;
+        for line in lines {
+            line_starts.push(offset);
+            offset += line.chars().count() + 1;
+        }
+
+        for (y, row) in rows.iter().skip(self.scroll as usize).take(area.height as usize).enumerate() {
+            let start = line_starts[row.line] + row.start;
+            let spans: Vec<Span> = lines[row.line].chars()
+                .skip(row.start)
+                .take(row.end - row.start)
+                .zip(&formats[start..])
+                .map(|(c, f)| {
+                    // Markers stay visible while typing, dimmed
+                    let style = f.style(Style::default(), code);
+                    Span::styled(c.to_string(), if f.marker { style.add_modifier(Modifier::DIM) } else { style })
+                })
+                .collect();
+            buf.set_line(area.x, area.y + y as u16, &Line::from(spans), area.width);
+        }
+
+        if show_cursor && cursor_col < area.width {
+            let position = (area.x + cursor_col, area.y + cursor_row - self.scroll);
+            buf[position].set_style(Style::default().add_modifier(Modifier::REVERSED));
+        }
+    }
+
+    /// Break every line into rows of at most `width` columns
+    ///
+    /// A line filling its last row exactly gets an empty row after it, where
+    /// the cursor goes when it's at the end of that line.
+    fn wrap(&self, width: u16) -> Vec<Row> {
+        let width = width.max(1) as usize;
+        let mut rows = Vec::new();
+        for (line, text) in self.textarea.lines().iter().enumerate() {
+            let (mut start, mut used, mut count) = (0, 0, 0);
+            for (i, c) in text.chars().enumerate() {
+                let char_width = c.width().unwrap_or(0);
+                if used + char_width > width && i > start {
+                    rows.push(Row { line, start, end: i });
+                    (start, used) = (i, 0);
+                }
+                used += char_width;
+                count = i + 1;
+            }
+            if used >= width {
+                rows.push(Row { line, start, end: count });
+                start = count;
+            }
+            rows.push(Row { line, start, end: count });
+        }
+        rows
+    }
+
+    /// Screen row and column of the cursor within the wrapped rows
+    fn cursor_position(&self, rows: &[Row]) -> (u16, u16) {
+        let (line, col) = self.textarea.cursor();
+        let Some((index, row)) = rows.iter()
+            .enumerate()
+            .rfind(|(_, row)| row.line == line && row.start <= col)
+        else {
+            return (0, 0);
+        };
+        let x: usize = self.textarea.lines()[line].chars()
+            .skip(row.start)
+            .take(col - row.start)
+            .map(|c| c.width().unwrap_or(0))
+            .sum();
+        (index as u16, x as u16)
+    }
+}
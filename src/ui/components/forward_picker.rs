@@ -0,0 +1,221 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, BorderType, Clear, List, ListItem, ListState, Paragraph, StatefulWidget, Widget},
+};
+
+use crate::ui::fuzzy;
+use crate::whatsapp::Chat;
+use super::super::Theme;
+
+/// Chats to forward messages to, picked from a list filtered as you type
+pub struct ForwardPicker {
+    message_ids: Vec<String>,  // Oldest first
+    query: String,
+    chosen: Vec<String>,  // Chat ids, in the order they were picked
+    list_state: ListState,
+}
+
+/// What a key did to the picker
+pub enum PickerInput {
+    Open,
+    Close,
+    Forward(Vec<String>, Vec<String>),  // Message ids, chat ids
+}
+
+impl ForwardPicker {
+    pub fn new(message_ids: Vec<String>) -> Self {
+        Self {
+            message_ids,
+            query: String::new(),
+            chosen: Vec::new(),
+            list_state: ListState::default().with_selected(Some(0)),
+        }
+    }
+
+    /// Middle of `area`, the message view
+    pub fn area(area: Rect) -> Rect {
+        let width = 48.min(area.width.saturating_sub(4));
+        let height = 16.min(area.height.saturating_sub(2));
+        Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        }
+    }
+
+    /// Pasted text filters like typing, on one line
+    pub fn paste(&mut self, text: &str) {
+        self.query.push_str(&text.split_whitespace().collect::<Vec<_>>().join(" "));
+        self.list_state.select(Some(0));
+    }
+
+    /// Arrows move, Space picks, Enter forwards, typing filters `chats`
+    pub fn input(&mut self, key: KeyEvent, chats: &[Chat]) -> PickerInput {
+        let candidates = self.candidates(chats);
+        let highlighted = self.list_state.selected().and_then(|i| candidates.get(i)).map(|&i| chats[i].id.clone());
+
+        match key.code {
+            KeyCode::Esc => return PickerInput::Close,
+
+            KeyCode::Up => {
+                let i = self.list_state.selected().unwrap_or(0);
+                self.list_state.select(Some(i.saturating_sub(1)));
+            }
+
+            KeyCode::Down => {
+                let last = candidates.len().saturating_sub(1);
+                let i = self.list_state.selected().map_or(0, |i| (i + 1).min(last));
+                self.list_state.select(Some(i));
+            }
+
+            // Space picks or unpicks the highlighted chat
+            KeyCode::Char(' ') => {
+                if let Some(chat_id) = highlighted {
+                    match self.chosen.iter().position(|id| *id == chat_id) {
+                        Some(i) => {
+                            self.chosen.remove(i);
+                        }
+                        None => self.chosen.push(chat_id),
+                    }
+                }
+            }
+
+            // Enter sends to the picked chats, or just the highlighted one
+            KeyCode::Enter => {
+                if self.chosen.is_empty() {
+                    self.chosen.extend(highlighted);
+                }
+                if self.chosen.is_empty() {
+                    return PickerInput::Close;
+                }
+                return PickerInput::Forward(std::mem::take(&mut self.message_ids), std::mem::take(&mut self.chosen));
+            }
+
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.list_state.select(Some(0));
+            }
+
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.query.push(c);
+                self.list_state.select(Some(0));
+            }
+
+            _ => {}
+        }
+
+        PickerInput::Open
+    }
+
+    /// Indexes into `chats` the picker lists: all of them, best match first while filtering
+    fn candidates(&self, chats: &[Chat]) -> Vec<usize> {
+        let mut matches: Vec<(i64, usize)> = chats.iter()
+            .enumerate()
+            .filter_map(|(i, chat)| fuzzy::chat_score(&self.query, chat).map(|score| (score, i)))
+            .collect();
+        matches.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        matches.into_iter().map(|(_, i)| i).collect()
+    }
+
+    pub fn render(&mut self, area: Rect, buf: &mut Buffer, chats: &[Chat], theme: &Theme) {
+        let title = match self.message_ids.len() {
+            1 => " Forward message ".to_string(),
+            n => format!(" Forward {} messages ", n),
+        };
+        let block = Block::default()
+            .title(title)
+            .title_bottom(" Space: pick, Enter: send, Esc: cancel ")
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(theme.border_focused));
+        let inner = block.inner(area);
+        Clear.render(area, buf);
+        block.render(area, buf);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .split(inner);
+
+        let chosen: Vec<&str> = self.chosen.iter()
+            .filter_map(|id| chats.iter().find(|c| c.id == *id))
+            .map(|c| c.name.as_str())
+            .collect();
+        let prompt = if self.query.is_empty() && !chosen.is_empty() {
+            Line::styled(format!("To: {}", chosen.join(", ")), Style::default().fg(theme.highlight))
+        } else {
+            Line::styled(format!("🔍 {}▏", self.query), Style::default().fg(theme.highlight))
+        };
+        Paragraph::new(prompt).render(chunks[0], buf);
+
+        let items: Vec<ListItem> = self.candidates(chats).into_iter()
+            .map(|i| {
+                let chat = &chats[i];
+                let checked = self.chosen.contains(&chat.id);
+                let mark = if checked { "[x] " } else { "[ ] " };
+                let style = if checked { Style::default().fg(theme.primary).add_modifier(Modifier::BOLD) } else { Style::default() };
+                ListItem::new(Line::from(vec![Span::styled(mark, style), Span::styled(chat.name.clone(), style)]))
+            })
+            .collect();
+        let list = List::new(items)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        StatefulWidget::render(list, chunks[1], buf, &mut self.list_state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn chat(id: &str, name: &str) -> Chat {
+        Chat {
+            id: id.to_string(),
+            name: name.to_string(),
+            is_group: false,
+            unread_count: 0,
+            archived: false,
+            timestamp: 0,
+            last_message: None,
+        }
+    }
+
+    #[test]
+    fn picks_chats_then_forwards() {
+        let chats = [chat("a@c.us", "Alice"), chat("b@c.us", "Bob"), chat("c@c.us", "Carol")];
+        let mut picker = ForwardPicker::new(vec!["m1".to_string()]);
+
+        // Typing filters, Space picks the highlighted chat
+        picker.paste("car");
+        assert!(matches!(picker.input(key(KeyCode::Char(' ')), &chats), PickerInput::Open));
+        for _ in 0..3 {
+            picker.input(key(KeyCode::Backspace), &chats);
+        }
+        picker.input(key(KeyCode::Down), &chats);
+        picker.input(key(KeyCode::Char(' ')), &chats);
+
+        match picker.input(key(KeyCode::Enter), &chats) {
+            PickerInput::Forward(message_ids, chat_ids) => {
+                assert_eq!(message_ids, ["m1"]);
+                assert_eq!(chat_ids, ["c@c.us", "b@c.us"]);
+            }
+            _ => panic!("Enter should forward to the picked chats"),
+        }
+    }
+
+    #[test]
+    fn enter_without_a_match_closes() {
+        let chats = [chat("a@c.us", "Alice")];
+        let mut picker = ForwardPicker::new(vec!["m1".to_string()]);
+        picker.paste("zzz");
+        assert!(matches!(picker.input(key(KeyCode::Enter), &chats), PickerInput::Close));
+    }
+}
@@ -0,0 +1,87 @@
+use image::{imageops::FilterType, Rgba, RgbaImage};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::Color,
+    widgets::Widget,
+};
+
+/// Pixels with less opacity than this show the terminal background
+const ALPHA_THRESHOLD: u8 = 128;
+
+/// Draws an image with `▀`/`▄` half-blocks, two truecolor pixels per cell
+///
+/// Works in any terminal with 24-bit color. Cells are about twice as tall
+/// as wide, so every half-block pixel is roughly square. The image is
+/// scaled to fill the area; use `fit` to pick an area that keeps its shape.
+pub struct ImageView<'a> {
+    image: &'a RgbaImage,
+}
+
+impl<'a> ImageView<'a> {
+    pub fn new(image: &'a RgbaImage) -> Self {
+        Self { image }
+    }
+}
+
+/// Largest size in cells within `max_cols` x `max_rows` that keeps the image's aspect ratio
+pub fn fit(width: u32, height: u32, max_cols: u16, max_rows: u16) -> (u16, u16) {
+    if width == 0 || height == 0 {
+        return (0, 0);
+    }
+
+    let max_width = max_cols as f64;
+    let max_height = max_rows as f64 * 2.0;
+    let scale = (max_width / width as f64).min(max_height / height as f64);
+
+    let cols = (width as f64 * scale).round().max(1.0) as u16;
+    let rows = ((height as f64 * scale).round() / 2.0).ceil().max(1.0) as u16;
+    (cols.min(max_cols), rows.min(max_rows))
+}
+
+impl Widget for ImageView<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let area = area.intersection(buf.area);
+        if area.is_empty() {
+            return;
+        }
+
+        // Already the right size (an odd height leaves the last half-row empty)
+        let (width, height) = (area.width as u32, area.height as u32 * 2);
+        let resized;
+        let pixels = if self.image.width() == width && self.image.height().div_ceil(2) == area.height as u32 {
+            self.image
+        } else {
+            resized = image::imageops::resize(self.image, width, height, FilterType::Triangle);
+            &resized
+        };
+
+        for row in 0..area.height {
+            for col in 0..area.width {
+                let (x, y) = (col as u32, row as u32 * 2);
+                let top = color(pixels.get_pixel_checked(x, y));
+                let bottom = color(pixels.get_pixel_checked(x, y + 1));
+
+                let cell = &mut buf[(area.x + col, area.y + row)];
+                match (top, bottom) {
+                    (None, None) => {
+                        cell.set_char(' ').set_fg(Color::Reset).set_bg(Color::Reset);
+                    }
+                    (None, Some(bottom)) => {
+                        cell.set_char('▄').set_fg(bottom).set_bg(Color::Reset);
+                    }
+                    (Some(top), bottom) => {
+                        cell.set_char('▀').set_fg(top).set_bg(bottom.unwrap_or(Color::Reset));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Truecolor for a pixel, None if it is transparent or outside the image
+fn color(pixel: Option<&Rgba<u8>>) -> Option<Color> {
+    pixel
+        .filter(|p| p[3] >= ALPHA_THRESHOLD)
+        .map(|p| Color::Rgb(p[0], p[1], p[2]))
+}
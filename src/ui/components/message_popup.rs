@@ -0,0 +1,346 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, BorderType, Clear, Paragraph, Widget},
+};
+use std::path::Path;
+
+use crate::ui::format;
+use crate::whatsapp::{AckStatus, Message};
+use super::super::Theme;
+
+/// Something to do with the selected message, from the menu or its shortcut
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageAction {
+    Copy,
+    Reply,
+    React,
+    Edit,
+    Delete,
+    Save,
+    Open,
+    OpenLink,
+    GoToQuoted,
+    Forward,
+    Details,
+}
+
+impl MessageAction {
+    pub const ALL: [MessageAction; 11] = [
+        MessageAction::Copy,
+        MessageAction::Reply,
+        MessageAction::React,
+        MessageAction::Edit,
+        MessageAction::Delete,
+        MessageAction::Save,
+        MessageAction::Open,
+        MessageAction::OpenLink,
+        MessageAction::GoToQuoted,
+        MessageAction::Forward,
+        MessageAction::Details,
+    ];
+
+    /// Shortcut while a message is selected, and in the menu
+    pub fn key(self) -> char {
+        match self {
+            MessageAction::Copy => 'c',
+            MessageAction::Reply => 'r',
+            MessageAction::React => '+',
+            MessageAction::Edit => 'e',
+            MessageAction::Delete => 'd',
+            MessageAction::Save => 's',
+            MessageAction::Open => 'o',
+            MessageAction::OpenLink => 'l',
+            MessageAction::GoToQuoted => 'g',
+            MessageAction::Forward => 'f',
+            MessageAction::Details => 'i',
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            MessageAction::Copy => "Copy text",
+            MessageAction::Reply => "Reply",
+            MessageAction::React => "React",
+            MessageAction::Edit => "Edit",
+            MessageAction::Delete => "Delete",
+            MessageAction::Save => "Save attachment",
+            MessageAction::Open => "Open attachment",
+            MessageAction::OpenLink => "Open link",
+            MessageAction::GoToQuoted => "Go to quoted message",
+            MessageAction::Forward => "Forward",
+            MessageAction::Details => "Details",
+        }
+    }
+
+    /// Whether the action makes sense for `msg`, so the menu offers it
+    pub fn applies_to(self, msg: &Message) -> bool {
+        match self {
+            MessageAction::Copy => !msg.deleted && !msg.body.is_empty(),
+            MessageAction::Reply | MessageAction::React | MessageAction::Delete | MessageAction::Forward => !msg.deleted,
+            MessageAction::Edit => msg.from_me && !msg.deleted && !msg.has_media,
+            MessageAction::Save | MessageAction::Open => msg.has_media && !msg.deleted,
+            MessageAction::OpenLink => !msg.deleted && !find_links(&msg.body).is_empty(),
+            MessageAction::GoToQuoted => msg.quoted.is_some(),
+            MessageAction::Details => true,
+        }
+    }
+}
+
+/// List popup over the message view, for the selected message
+pub enum MessageMenu {
+    Actions(Vec<MessageAction>, usize),  // Highlighted index
+    Links(Vec<String>, usize),
+}
+
+/// What a key did to an open menu
+pub enum MenuInput {
+    Open,
+    Close,
+    Run(MessageAction),
+    OpenLink(String),
+}
+
+impl MessageMenu {
+    /// The actions that apply to `msg`, the first highlighted
+    pub fn actions(msg: &Message) -> Self {
+        let actions = MessageAction::ALL.into_iter().filter(|a| a.applies_to(msg)).collect();
+        MessageMenu::Actions(actions, 0)
+    }
+
+    pub fn links(links: Vec<String>) -> Self {
+        MessageMenu::Links(links, 0)
+    }
+
+    /// Arrows move, Enter picks, an action's key or a link's number picks right away
+    pub fn input(&mut self, key: KeyEvent) -> MenuInput {
+        let (len, index) = match self {
+            MessageMenu::Actions(actions, index) => (actions.len(), index),
+            MessageMenu::Links(links, index) => (links.len(), index),
+        };
+
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => *index = index.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => *index = (*index + 1).min(len.saturating_sub(1)),
+            KeyCode::Esc => return MenuInput::Close,
+
+            KeyCode::Enter => return match self {
+                MessageMenu::Actions(actions, index) => actions.get(*index).map_or(MenuInput::Close, |&a| MenuInput::Run(a)),
+                MessageMenu::Links(links, index) => links.get(*index).map_or(MenuInput::Close, |url| MenuInput::OpenLink(url.clone())),
+            },
+
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let picked = match self {
+                    MessageMenu::Actions(actions, _) => actions.iter().find(|a| a.key() == c).map(|&a| MenuInput::Run(a)),
+                    MessageMenu::Links(links, _) => c.to_digit(10)
+                        .and_then(|n| links.get((n as usize).checked_sub(1)?))
+                        .map(|url| MenuInput::OpenLink(url.clone())),
+                };
+                if let Some(picked) = picked {
+                    return picked;
+                }
+            }
+
+            _ => {}
+        }
+
+        MenuInput::Open
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            MessageMenu::Actions(..) => " Message (Enter: run, Esc: close) ",
+            MessageMenu::Links(..) => " Open link (Enter: open, Esc: close) ",
+        }
+    }
+
+    /// One line per entry, its shortcut first and the highlighted one marked
+    pub fn lines(&self, theme: &Theme) -> Vec<Line<'static>> {
+        let key_style = Style::default().fg(theme.primary).add_modifier(Modifier::BOLD);
+        let selected_style = Style::default().bg(theme.primary).add_modifier(Modifier::BOLD);
+
+        match self {
+            MessageMenu::Actions(actions, index) => actions.iter()
+                .enumerate()
+                .map(|(i, action)| {
+                    let style = if i == *index { selected_style } else { Style::default() };
+                    Line::from(vec![
+                        Span::styled(format!(" {} ", action.key()), key_style),
+                        Span::styled(format!(" {} ", action.label()), style),
+                    ])
+                })
+                .collect(),
+            MessageMenu::Links(links, index) => links.iter()
+                .enumerate()
+                .map(|(i, url)| {
+                    let style = if i == *index { selected_style } else { Style::default() };
+                    let number = if i < 9 { format!(" {} ", i + 1) } else { "   ".to_string() };
+                    Line::from(vec![
+                        Span::styled(number, key_style),
+                        Span::styled(format!(" {} ", url), style),
+                    ])
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Everything known about a message, for the details popup
+///
+/// What depends on the app's state comes in ready to show: the sent time in
+/// the configured format, and the reactions aggregated like under the message.
+pub struct MessageDetails<'a> {
+    pub message: &'a Message,
+    pub chat: &'a str,
+    pub sent: String,
+    pub saved_at: Option<&'a Path>,
+    pub reactions: Option<Line<'static>>,
+}
+
+impl MessageDetails<'_> {
+    pub const TITLE: &'static str = " Message details (any key: close) ";
+
+    /// One fact per line
+    pub fn lines(self, theme: &Theme) -> Vec<Line<'static>> {
+        let msg = self.message;
+        let label = |name: &str| Span::styled(format!(" {:<10}", name), Style::default().fg(theme.system));
+        let from = if msg.from_me {
+            "Me".to_string()
+        } else {
+            msg.sender.clone().unwrap_or_else(|| self.chat.to_string())
+        };
+
+        let mut lines = vec![
+            Line::from(vec![label("Chat"), Span::raw(format!("{} ", self.chat))]),
+            Line::from(vec![label("From"), Span::raw(format!("{} ", from))]),
+            Line::from(vec![label("Sent"), Span::raw(format!("{} ", self.sent))]),
+        ];
+        if msg.from_me {
+            let status = match msg.ack {
+                AckStatus::Error => "Failed",
+                AckStatus::Pending => "Pending",
+                AckStatus::Sent => "Sent",
+                AckStatus::Delivered => "Delivered",
+                AckStatus::Read => "Read",
+                AckStatus::Played => "Played",
+            };
+            lines.push(Line::from(vec![label("Status"), Span::raw(format!("{} ", status))]));
+        }
+        if msg.has_media {
+            let mut media = msg.media_type.clone().unwrap_or_else(|| "unknown".to_string());
+            if let Some(path) = self.saved_at {
+                media.push_str(&format!(", saved at {}", path.display()));
+            }
+            lines.push(Line::from(vec![label("Media"), Span::raw(format!("{} ", media))]));
+        }
+        if let Some(quoted) = &msg.quoted {
+            let author = if quoted.from_me { "Me".to_string() } else { quoted.sender.clone().unwrap_or_else(|| "User".to_string()) };
+            lines.push(Line::from(vec![label("Reply to"), Span::raw(format!("{}: {} ", author, format::snippet(&quoted.body, 40)))]));
+        }
+        if let Some(mut line) = self.reactions {
+            line.spans[0] = label("Reactions");  // In place of the indent
+            lines.push(line);
+        }
+        if msg.edited {
+            lines.push(Line::from(vec![label("Edited"), Span::raw("Yes ")]));
+        }
+        if msg.forwarded {
+            lines.push(Line::from(vec![label("Forwarded"), Span::raw("Yes ")]));
+        }
+        if msg.deleted {
+            lines.push(Line::from(vec![label("Deleted"), Span::raw("For everyone ")]));
+        }
+        lines.push(Line::from(vec![label("ID"), Span::raw(format!("{} ", msg.id))]));
+        lines
+    }
+}
+
+/// Bordered popup over the message view: a menu, a prompt or the details
+pub struct MessagePopup {
+    title: String,
+    lines: Vec<Line<'static>>,
+}
+
+impl MessagePopup {
+    pub fn new(title: impl Into<String>, lines: Vec<Line<'static>>) -> Self {
+        Self {
+            title: title.into(),
+            lines,
+        }
+    }
+
+    /// Along the bottom of `area`, just big enough for the title and lines
+    pub fn area(&self, area: Rect) -> Rect {
+        let content_width = self.lines.iter().map(Line::width).max().unwrap_or(0).max(Line::raw(&self.title).width());
+        let width = (content_width as u16 + 2).min(area.width.saturating_sub(2));
+        let height = (self.lines.len() as u16 + 2).min(area.height.saturating_sub(2));
+        Rect {
+            x: area.x + 1,
+            y: (area.y + area.height).saturating_sub(height + 1).max(area.y),
+            width,
+            height,
+        }
+    }
+
+    pub fn render(self, area: Rect, buf: &mut Buffer, theme: &Theme) {
+        let widget = Paragraph::new(self.lines)
+            .block(Block::default()
+                .title(self.title)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(theme.border_focused)));
+
+        Clear.render(area, buf);
+        widget.render(area, buf);
+    }
+}
+
+/// Web links in a message body, each once, in order
+pub fn find_links(text: &str) -> Vec<String> {
+    let mut links: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        let Some(start) = word.find("https://").or_else(|| word.find("http://")) else {
+            continue;
+        };
+        // Punctuation after a link belongs to the sentence
+        let link = word[start..].trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']', '}', '"', '\'', '>', '*', '_', '~']);
+        if link.len() > "https://".len() && !links.iter().any(|l| l == link) {
+            links.push(link.to_string());
+        }
+    }
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn links_found_once_without_trailing_punctuation() {
+        let links = find_links("see https://example.com/a, and (http://example.org/b). https://example.com/a again, not https://");
+        assert_eq!(links, ["https://example.com/a", "http://example.org/b"]);
+    }
+
+    #[test]
+    fn menu_keys() {
+        let mut menu = MessageMenu::Actions(vec![MessageAction::Copy, MessageAction::Reply], 0);
+        assert!(matches!(menu.input(key(KeyCode::Down)), MenuInput::Open));
+        assert!(matches!(menu.input(key(KeyCode::Down)), MenuInput::Open));  // Stays on the last
+        assert!(matches!(menu.input(key(KeyCode::Enter)), MenuInput::Run(MessageAction::Reply)));
+        assert!(matches!(menu.input(key(KeyCode::Char('c'))), MenuInput::Run(MessageAction::Copy)));
+        assert!(matches!(menu.input(key(KeyCode::Char('d'))), MenuInput::Open));  // Not offered
+        assert!(matches!(menu.input(key(KeyCode::Esc)), MenuInput::Close));
+
+        let mut menu = MessageMenu::links(vec!["https://a.example".to_string(), "https://b.example".to_string()]);
+        assert!(matches!(menu.input(key(KeyCode::Char('2'))), MenuInput::OpenLink(url) if url == "https://b.example"));
+        assert!(matches!(menu.input(key(KeyCode::Char('3'))), MenuInput::Open));
+        assert!(matches!(menu.input(key(KeyCode::Char('0'))), MenuInput::Open));
+    }
+}
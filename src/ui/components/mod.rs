@@ -1 +1,5 @@
+pub mod composer;
+pub mod forward_picker;
+pub mod image_view;
+pub mod message_popup;
 pub mod qr_view;
@@ -1,81 +1,100 @@
+use image::{Rgba, RgbaImage};
 use qrcode::QrCode;
 use ratatui::{
-    layout::{Alignment, Constraint, Direction, Layout},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, BorderType, Paragraph, Widget},
+    widgets::{Block, Borders, BorderType, Paragraph, Widget, Wrap},
 };
 
 use super::super::Theme;
+use super::image_view::ImageView;
+
+/// Light modules around the code, in modules
+const QUIET_ZONE: u32 = 2;
 
 pub struct QRView<'a> {
     qr_data: &'a str,
     status: &'a str,
     theme: &'a Theme,
+    truecolor: bool,  // Black on white pixels; otherwise glyphs in the terminal's own colors
 }
 
 impl<'a> QRView<'a> {
     pub fn new(qr_data: &'a str, status: &'a str, theme: &'a Theme) -> Self {
+        let truecolor = std::env::var("COLORTERM")
+            .is_ok_and(|v| v == "truecolor" || v == "24bit");
         Self {
             qr_data,
             status,
             theme,
+            truecolor,
         }
     }
     
-    fn generate_qr_text(&self) -> Vec<Line<'a>> {
-        match QrCode::new(self.qr_data) {
-            Ok(qr) => {
-                // Use unicode half-block characters to make QR codes more square
-                // Terminal cells are roughly 1:2 (width:height), so we use half blocks
-                let colors = qr.to_colors();
-                let width = (colors.len() as f64).sqrt() as usize;
-                
-                let mut lines = Vec::new();
-                
-                // Process two rows at a time using half-blocks
-                for y in (0..width).step_by(2) {
-                    let mut line_str = String::new();
-                    line_str.push_str("  "); // Small padding
-                    
-                    for x in 0..width {
-                        use qrcode::Color;
-                        let top = match colors[x + y * width] {
-                            Color::Dark => true,
-                            Color::Light => false,
-                        };
-                        let bottom = if y + 1 < width {
-                            match colors[x + (y + 1) * width] {
-                                Color::Dark => true,
-                                Color::Light => false,
-                            }
-                        } else {
-                            false
-                        };
-                        
-                        // Use Unicode half-block characters
-                        let ch = match (top, bottom) {
-                            (false, false) => ' ',  // Both white
-                            (true, true) => '█',    // Both black  
-                            (true, false) => '▀',   // Top black, bottom white
-                            (false, true) => '▄',   // Top white, bottom black
-                        };
-                        line_str.push(ch);
-                    }
-                    
-                    line_str.push_str("  "); // Small padding
-                    lines.push(Line::from(line_str));
-                }
-                
-                lines
-            }
-            Err(_) => {
-                vec![Line::from("Failed to generate QR code")]
+    /// One pixel per module, dark on white with a quiet zone so phones can scan it
+    fn generate_qr_image(&self) -> Option<RgbaImage> {
+        let qr = QrCode::new(self.qr_data).ok()?;
+        let modules = qr.width() as u32;
+        let colors = qr.to_colors();
+        let size = modules + QUIET_ZONE * 2;
+
+        Some(RgbaImage::from_fn(size, size, |x, y| {
+            let dark = x >= QUIET_ZONE && y >= QUIET_ZONE
+                && x < QUIET_ZONE + modules && y < QUIET_ZONE + modules
+                && colors[((y - QUIET_ZONE) * modules + (x - QUIET_ZONE)) as usize] == qrcode::Color::Dark;
+            if dark { Rgba([0, 0, 0, 255]) } else { Rgba([255, 255, 255, 255]) }
+        }))
+    }
+
+    /// Half-block glyphs for terminals without truecolor, one module per half-cell
+    fn generate_qr_text(&self) -> Option<Vec<Line<'a>>> {
+        let qr = QrCode::new(self.qr_data).ok()?;
+        // Use unicode half-block characters to make QR codes more square
+        // Terminal cells are roughly 1:2 (width:height), so we use half blocks
+        let colors = qr.to_colors();
+        let width = qr.width();
+
+        let mut lines = Vec::new();
+
+        // Process two rows at a time using half-blocks
+        for y in (0..width).step_by(2) {
+            let mut line_str = String::new();
+            line_str.push_str("  "); // Small padding
+
+            for x in 0..width {
+                use qrcode::Color;
+                let top = colors[x + y * width] == Color::Dark;
+                let bottom = y + 1 < width && colors[x + (y + 1) * width] == Color::Dark;
+
+                // Use Unicode half-block characters
+                let ch = match (top, bottom) {
+                    (false, false) => ' ',  // Both white
+                    (true, true) => '█',    // Both black
+                    (true, false) => '▀',   // Top black, bottom white
+                    (false, true) => '▄',   // Top white, bottom black
+                };
+                line_str.push(ch);
             }
+
+            line_str.push_str("  "); // Small padding
+            lines.push(Line::from(line_str));
         }
+
+        Some(lines)
     }
 }
 
+/// `width` x `height` cells centered in `area`, None if they don't fit
+fn centered(area: Rect, width: u16, height: u16) -> Option<Rect> {
+    (width <= area.width && height <= area.height).then(|| Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    })
+}
+
 impl<'a> Widget for QRView<'a> {
     fn render(self, area: ratatui::layout::Rect, buf: &mut ratatui::buffer::Buffer) {
         // Calculate proper layout based on terminal size
@@ -104,15 +123,51 @@ impl<'a> Widget for QRView<'a> {
         title.render(chunks[0], buf);
         
         // QR Code - centered and properly sized
-        let qr_lines = self.generate_qr_text();
-        let qr = Paragraph::new(qr_lines)
-            .alignment(Alignment::Center)
-            .block(Block::default()
-                .borders(Borders::ALL)
-                .title(" Scan this code ")
-                .border_type(BorderType::Rounded)
-                .border_style(Style::default().fg(self.theme.border)));
-        qr.render(chunks[1], buf);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(" Scan this code ")
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(self.theme.border));
+        let inner = block.inner(chunks[1]);
+        block.render(chunks[1], buf);
+
+        // Always one module per half-cell: a resampled code doesn't scan
+        let (size, drawn) = if self.truecolor {
+            match self.generate_qr_image() {
+                Some(qr) => {
+                    let size = (qr.width() as u16, qr.height().div_ceil(2) as u16);
+                    let area = centered(inner, size.0, size.1);
+                    if let Some(area) = area {
+                        ImageView::new(&qr).render(area, buf);
+                    }
+                    (Some(size), area.is_some())
+                }
+                None => (None, false),
+            }
+        } else {
+            match self.generate_qr_text() {
+                Some(lines) => {
+                    let size = (lines.first().map_or(0, |l| l.width()) as u16, lines.len() as u16);
+                    let area = centered(inner, size.0, size.1);
+                    if let Some(area) = area {
+                        Paragraph::new(lines).render(area, buf);
+                    }
+                    (Some(size), area.is_some())
+                }
+                None => (None, false),
+            }
+        };
+
+        if !drawn {
+            let message = match size {
+                Some((width, height)) => format!("Enlarge the terminal to show the QR code ({}x{})", width, height),
+                None => "Failed to generate QR code".to_string(),
+            };
+            Paragraph::new(message)
+                .alignment(Alignment::Center)
+                .wrap(Wrap { trim: true })
+                .render(inner, buf);
+        }
         
         // Instructions - more compact
         let instructions = vec![
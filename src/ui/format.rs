@@ -0,0 +1,242 @@
+//! WhatsApp text formatting: `*bold*`, `_italic_`, `~strike~`, `` `code` ``
+//! and ```` ```monospace``` ````
+//!
+//! Like the official clients, a marker only counts at a word boundary: it
+//! opens after a space, punctuation or the start of the text and before a
+//! non-space, and closes after a non-space and before a space, punctuation
+//! or the end. So `2*3*4` and `snake_case_name` stay as typed. Bold, italic
+//! and strike nest but don't span lines, code turns formatting off inside
+//! it, and elsewhere a backslash before a marker keeps it literal.
+
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Span;
+
+/// How one character of a text renders
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Format {
+    pub bold: bool,
+    pub italic: bool,
+    pub strike: bool,
+    pub code: bool,
+    pub marker: bool,  // Formatting syntax: hidden in messages, dimmed while typing
+}
+
+impl Format {
+    /// `base` with this format's modifiers, code in `code`'s colors
+    pub fn style(self, base: Style, code: Style) -> Style {
+        let mut style = if self.code { base.patch(code) } else { base };
+        if self.bold {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        if self.italic {
+            style = style.add_modifier(Modifier::ITALIC);
+        }
+        if self.strike {
+            style = style.add_modifier(Modifier::CROSSED_OUT);
+        }
+        style
+    }
+}
+
+/// The format of every char of `text`, in order
+pub fn parse(text: &str) -> Vec<Format> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut formats = vec![Format::default(); chars.len()];
+
+    // Monospace blocks first, they can span lines and take everything inside,
+    // backslashes included
+    let mut literal = vec![false; chars.len()];
+    let mut i = 0;
+    while let Some(open) = find_run(&chars, &literal, i, "```") {
+        let Some(close) = find_run(&chars, &literal, open + 4, "```") else {
+            set(&mut literal, open..open + 3, |l| *l = true);
+            break;
+        };
+        set(&mut formats, open..close + 3, |f| *f = Format { code: true, ..Format::default() });
+        set(&mut formats, open..open + 3, |f| f.marker = true);
+        set(&mut formats, close..close + 3, |f| f.marker = true);
+        set(&mut literal, open..close + 3, |l| *l = true);
+        i = close + 3;
+    }
+
+    // Inline code next, so a backslash inside it stays as typed
+    pair_markers(&chars, &mut literal, &mut formats, '`', |f| f.code = true, true);
+
+    // Outside code, escaped markers are plain text and their backslash is syntax
+    for i in 1..chars.len() {
+        if chars[i - 1] == '\\' && !literal[i - 1] && !literal[i] && is_marker(chars[i]) {
+            formats[i - 1].marker = true;
+            literal[i - 1] = true;
+            literal[i] = true;
+        }
+    }
+
+    // Then the rest, which don't apply inside code
+    for (marker, apply) in [
+        ('*', (|f: &mut Format| f.bold = true) as fn(&mut Format)),
+        ('_', |f: &mut Format| f.italic = true),
+        ('~', |f: &mut Format| f.strike = true),
+    ] {
+        pair_markers(&chars, &mut literal, &mut formats, marker, apply, false);
+    }
+
+    formats
+}
+
+/// `text` as spans in `base`, markers left out
+pub fn spans(text: &str, base: Style, code: Style) -> Vec<Span<'static>> {
+    let formats = parse(text);
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    let mut current: Option<(Format, String)> = None;
+
+    for (c, format) in text.chars().zip(formats) {
+        if format.marker {
+            continue;
+        }
+        match &mut current {
+            Some((f, run)) if *f == format => run.push(c),
+            _ => {
+                if let Some((f, run)) = current.take() {
+                    spans.push(Span::styled(run, f.style(base, code)));
+                }
+                current = Some((format, c.to_string()));
+            }
+        }
+    }
+    if let Some((f, run)) = current {
+        spans.push(Span::styled(run, f.style(base, code)));
+    }
+    spans
+}
+
+/// First line of a message body, cut to `max_chars`
+pub fn snippet(body: &str, max_chars: usize) -> String {
+    let first_line = body.lines().next().unwrap_or_default();
+    if first_line.chars().count() > max_chars || body.lines().nth(1).is_some() {
+        let cut: String = first_line.chars().take(max_chars).collect();
+        format!("{}…", cut.trim_end())
+    } else {
+        first_line.to_string()
+    }
+}
+
+fn is_marker(c: char) -> bool {
+    matches!(c, '*' | '_' | '~' | '`')
+}
+
+/// Pair up `marker`s that open and close at word boundaries, applying `apply` to what's between
+///
+/// Paired markers and, for code, everything inside become `literal` so other markers skip them.
+fn pair_markers(
+    chars: &[char],
+    literal: &mut [bool],
+    formats: &mut [Format],
+    marker: char,
+    apply: fn(&mut Format),
+    is_code: bool,
+) {
+    let mut i = 0;
+    while i < chars.len() {
+        if !opens(chars, literal, i, marker) {
+            i += 1;
+            continue;
+        }
+
+        // The nearest closing marker on the same line
+        let close = (i + 2..chars.len())
+            .take_while(|&j| chars[j] != '\n')
+            .find(|&j| closes(chars, literal, j, marker));
+        let Some(close) = close else {
+            i += 1;
+            continue;
+        };
+
+        if is_code {
+            set(formats, i + 1..close, |f| f.marker = false);
+        }
+        set(formats, i + 1..close, apply);
+        formats[i].marker = true;
+        formats[close].marker = true;
+        literal[i] = true;
+        literal[close] = true;
+        if is_code {
+            set(literal, i..close + 1, |l| *l = true);
+        }
+        i = close + 1;
+    }
+}
+
+fn opens(chars: &[char], literal: &[bool], i: usize, marker: char) -> bool {
+    chars[i] == marker
+        && !literal[i]
+        && (i == 0 || !chars[i - 1].is_alphanumeric())
+        && chars.get(i + 1).is_some_and(|&c| !c.is_whitespace() && c != marker)
+}
+
+fn closes(chars: &[char], literal: &[bool], j: usize, marker: char) -> bool {
+    chars[j] == marker
+        && !literal[j]
+        && !chars[j - 1].is_whitespace()
+        && chars.get(j + 1).is_none_or(|c| !c.is_alphanumeric())
+}
+
+/// Start of the next unescaped `run` (e.g. "```") at or after `from`
+fn find_run(chars: &[char], literal: &[bool], from: usize, run: &str) -> Option<usize> {
+    let run: Vec<char> = run.chars().collect();
+    (from..chars.len().saturating_sub(run.len() - 1))
+        .find(|&i| chars[i..i + run.len()] == run[..] && !literal[i..i + run.len()].contains(&true))
+}
+
+fn set<T>(items: &mut [T], range: std::ops::Range<usize>, apply: impl Fn(&mut T)) {
+    items[range].iter_mut().for_each(apply);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs of visible text with their format: "b"old, "i"talic, "s"trike, "c"ode
+    fn runs(text: &str) -> Vec<(String, String)> {
+        let mut runs: Vec<(String, String)> = Vec::new();
+        for (c, f) in text.chars().zip(parse(text)) {
+            if f.marker {
+                continue;
+            }
+            let flags: String = [(f.bold, 'b'), (f.italic, 'i'), (f.strike, 's'), (f.code, 'c')]
+                .iter()
+                .filter(|(on, _)| *on)
+                .map(|(_, flag)| *flag)
+                .collect();
+            match runs.last_mut() {
+                Some((run, run_flags)) if *run_flags == flags => run.push(c),
+                _ => runs.push((c.to_string(), flags)),
+            }
+        }
+        runs
+    }
+
+    #[test]
+    fn parses() {
+        let cases: &[(&str, &[(&str, &str)])] = &[
+            ("*bold* _it_ ~gone~", &[("bold", "b"), (" ", ""), ("it", "i"), (" ", ""), ("gone", "s")]),
+            ("2*3*4", &[("2*3*4", "")]),
+            ("snake_case_name", &[("snake_case_name", "")]),
+            ("*bold*text", &[("*bold*text", "")]),
+            ("\\*literal*", &[("*literal*", "")]),
+            ("_*x*_", &[("x", "bi")]),
+            ("*a\nb*", &[("*a\nb*", "")]),
+            ("```unclosed *b*", &[("```unclosed ", ""), ("b", "b")]),
+            ("```a\n*b*```", &[("a\n*b*", "c")]),
+            ("`*a*`", &[("*a*", "c")]),
+            // Code comes before escapes: the backslash is code, so the span ends after it
+            ("`a\\` b`", &[("a\\", "c"), (" b`", "")]),
+        ];
+
+        for (text, expected) in cases {
+            let expected: Vec<(String, String)> = expected.iter()
+                .map(|(run, flags)| (run.to_string(), flags.to_string()))
+                .collect();
+            assert_eq!(runs(text), expected, "{:?}", text);
+        }
+    }
+}
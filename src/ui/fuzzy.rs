@@ -0,0 +1,118 @@
+//! Fuzzy matching for filtering lists as you type
+//!
+//! The query's characters must appear in order in the candidate, ignoring
+//! case. Matches score higher when they are consecutive or start words, so
+//! "wh" ranks "Whatever happened" above "Weekend Hike", and both above
+//! "Sandwich".
+
+use crate::whatsapp::Chat;
+
+/// Points for every matched character
+const MATCH: i64 = 16;
+/// Extra points when a match directly follows the previous one
+const CONSECUTIVE: i64 = 24;
+/// Extra points when a match starts a word
+const WORD_START: i64 = 20;
+/// Points lost per skipped character between two matches, up to `MAX_GAP`
+const GAP: i64 = 2;
+const MAX_GAP: i64 = 20;
+
+/// Score of `query` against `candidate` and the matched char positions, None if it doesn't match
+///
+/// An empty query matches everything with a score of 0.
+pub fn find(query: &str, candidate: &str) -> Option<(i64, Vec<usize>)> {
+    let query: Vec<char> = query.chars().map(fold_case).collect();
+    let chars: Vec<char> = candidate.chars().collect();
+    let lower: Vec<char> = chars.iter().copied().map(fold_case).collect();
+    let Some(&first) = query.first() else {
+        return Some((0, Vec::new()));
+    };
+
+    // Greedy from every place the first character matches, keep the best
+    let mut best: Option<(i64, Vec<usize>)> = None;
+    for start in (0..lower.len()).filter(|&i| lower[i] == first) {
+        let Some(positions) = match_from(&query, &lower, start) else {
+            break;  // Later starts can't match either
+        };
+        let score = score(&chars, &positions);
+        if best.as_ref().is_none_or(|(best_score, _)| score > *best_score) {
+            best = Some((score, positions));
+        }
+    }
+    best
+}
+
+/// Filter score of a chat: the better of its name and, for contacts, its phone number
+pub fn chat_score(query: &str, chat: &Chat) -> Option<i64> {
+    let name = find(query, &chat.name).map(|(score, _)| score);
+
+    // Contact ids are their phone number, "15551234567@c.us"
+    let phone_like = query.chars().any(|c| c.is_ascii_digit())
+        && query.chars().all(|c| c.is_ascii_digit() || "+-() ".contains(c));
+    let phone = (phone_like && !chat.is_group)
+        .then(|| query.chars().filter(char::is_ascii_digit).collect::<String>())
+        .and_then(|digits| find(&digits, chat.id.split('@').next().unwrap_or_default()))
+        .map(|(score, _)| score);
+
+    name.max(phone)
+}
+
+/// One lowercase char for `c`, so positions line up with the original
+pub fn fold_case(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Positions of `query` in `lower`, matching the first character at `start`
+fn match_from(query: &[char], lower: &[char], start: usize) -> Option<Vec<usize>> {
+    let mut positions = vec![start];
+    let mut next = start + 1;
+    for &c in &query[1..] {
+        let found = lower[next..].iter().position(|&l| l == c)? + next;
+        positions.push(found);
+        next = found + 1;
+    }
+    Some(positions)
+}
+
+fn score(chars: &[char], positions: &[usize]) -> i64 {
+    let mut score = 0;
+    let mut previous: Option<usize> = None;
+    for &i in positions {
+        score += MATCH;
+        if i == 0 || !chars[i - 1].is_alphanumeric() {
+            score += WORD_START;
+        }
+        match previous {
+            Some(p) if p + 1 == i => score += CONSECUTIVE,
+            Some(p) => score -= (GAP * (i - p - 1) as i64).min(MAX_GAP),
+            None => score -= (GAP * i as i64).min(MAX_GAP),
+        }
+        previous = Some(i);
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score_of(query: &str, candidate: &str) -> i64 {
+        find(query, candidate).map(|(score, _)| score).unwrap()
+    }
+
+    #[test]
+    fn consecutive_beats_word_starts_beats_mid_word() {
+        let whatever = score_of("wh", "Whatever happened");
+        let weekend = score_of("wh", "Weekend Hike");
+        let sandwich = score_of("wh", "Sandwich");
+        assert!(whatever > weekend, "{} vs {}", whatever, weekend);
+        assert!(weekend > sandwich, "{} vs {}", weekend, sandwich);
+    }
+
+    #[test]
+    fn matches_in_order_ignoring_case() {
+        assert_eq!(find("WH", "weekend hike").map(|(_, p)| p), Some(vec![0, 8]));
+        assert_eq!(find("hw", "Weekend"), None);
+        assert_eq!(find("", "Anything"), Some((0, Vec::new())));
+    }
+}
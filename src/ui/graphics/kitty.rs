@@ -0,0 +1,61 @@
+//! Kitty graphics protocol: https://sw.kovidgoyal.net/kitty/graphics-protocol/
+//!
+//! Every command passes `q=2` so the terminal never answers; a reply would
+//! end up in crossterm's input stream.
+
+use base64::Engine;
+use image::RgbaImage;
+use ratatui::layout::Rect;
+use std::io::{self, Write};
+
+/// Base64 bytes per escape sequence, the maximum the protocol allows
+const CHUNK_SIZE: usize = 4096;
+
+/// Upload raw RGBA pixels as image `id`
+pub fn transmit(out: &mut impl Write, id: u32, image: &RgbaImage) -> io::Result<()> {
+    let data = base64::engine::general_purpose::STANDARD.encode(image.as_raw());
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(CHUNK_SIZE).collect();
+
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        if i == 0 {
+            write!(out, "\x1b_Ga=t,f=32,s={},v={},i={},q=2,m={};", image.width(), image.height(), id, more)?;
+        } else {
+            write!(out, "\x1b_Gm={};", more)?;
+        }
+        out.write_all(chunk)?;
+        out.write_all(b"\x1b\\")?;
+    }
+
+    Ok(())
+}
+
+/// Show image `id` scaled to `area`, replacing its previous placement
+pub fn place(out: &mut impl Write, id: u32, area: Rect) -> io::Result<()> {
+    // Save and restore the cursor so ratatui's idea of it stays right
+    write!(
+        out,
+        "\x1b7\x1b[{};{}H\x1b_Ga=p,i={},p=1,c={},r={},C=1,q=2\x1b\\\x1b8",
+        area.y + 1, area.x + 1, id, area.width, area.height,
+    )
+}
+
+/// Remove image `id` from the screen, keeping its data for later
+pub fn delete_placement(out: &mut impl Write, id: u32) -> io::Result<()> {
+    write!(out, "\x1b_Ga=d,d=i,i={},p=1,q=2\x1b\\", id)
+}
+
+/// Remove image `id` and free its data
+pub fn delete_image(out: &mut impl Write, id: u32) -> io::Result<()> {
+    write!(out, "\x1b_Ga=d,d=I,i={},q=2\x1b\\", id)
+}
+
+/// Remove every placement, keeping image data
+pub fn delete_all_placements(out: &mut impl Write) -> io::Result<()> {
+    write!(out, "\x1b_Ga=d,d=a,q=2\x1b\\")
+}
+
+/// Remove every placement and free all image data
+pub fn delete_all(out: &mut impl Write) -> io::Result<()> {
+    write!(out, "\x1b_Ga=d,d=A,q=2\x1b\\")
+}
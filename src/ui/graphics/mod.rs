@@ -0,0 +1,475 @@
+//! Inline image previews in the message view, and chat avatars
+//!
+//! The message view reserves blank rows for each preview and hands their area
+//! to `Graphics::draw`. Half-block previews are drawn into the frame right
+//! away. Kitty and Sixel images are written straight to the terminal after
+//! ratatui has drawn the frame: `Graphics::flush` moves, adds and removes
+//! placements so the screen matches it. Avatars are too small for anything
+//! but Kitty, elsewhere the chat list shows initials instead.
+
+mod kitty;
+mod sixel;
+
+use anyhow::{Context, Result};
+use image::RgbaImage;
+use ratatui::{buffer::Buffer, layout::Rect, widgets::Widget};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::MediaConfig;
+use super::components::image_view::{self, ImageView};
+
+/// Largest preview, in cells
+const MAX_COLS: u16 = 32;
+const MAX_ROWS: u16 = 10;
+
+/// Kitty images kept in terminal memory; the least recently shown go first
+const MAX_KITTY_IMAGES: usize = 64;
+
+/// Avatars are scaled down to this many pixels square
+const AVATAR_PX: u32 = 64;
+
+/// Cell size in pixels when the terminal doesn't report one
+const DEFAULT_CELL_SIZE: (u16, u16) = (8, 16);
+
+/// Extra wait for a reply that missed the query timeout, so it isn't read as keys
+const LATE_REPLY_GRACE: Duration = Duration::from_millis(100);
+
+/// How previews get on screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Kitty,
+    Sixel,
+    HalfBlocks,  // Works everywhere, at two pixels per cell
+}
+
+/// An inline image: a message's preview or a chat's avatar
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ImageKey {
+    Preview(String),  // message id
+    Avatar(String),  // chat id
+}
+
+/// Where an image is drawn in the current frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Placement {
+    key: ImageKey,
+    area: Rect,
+}
+
+pub struct Graphics {
+    protocol: Protocol,
+    cell_size: (u16, u16),  // Pixels per cell (width, height)
+    images: HashMap<ImageKey, Option<Arc<RgbaImage>>>,  // Decoded images, None if there is nothing to show
+    requested: HashSet<ImageKey>,  // Images being fetched or decoded
+    missing: Vec<ImageKey>,  // Images the last frame asked for that aren't loaded yet
+    image_ids: HashMap<ImageKey, (u32, u64)>,  // Image ids already sent to the terminal, and the flush they were last shown in
+    flushes: u64,  // Kitty flushes so far, to age `image_ids`
+    sixels: HashMap<ImageKey, String>,  // Encoded Sixel sequences
+    next_image_id: u32,
+    wanted: Vec<Placement>,  // Placements of the frame being rendered
+    placed: Vec<Placement>,  // Placements on screen right now
+    reset: bool,  // Screen was cleared, forget what we think is on it
+}
+
+/// The configured image protocol, or the best one the terminal supports
+///
+/// May query the terminal, so call it once before the UI starts reading input.
+pub fn detect_protocol(config: &MediaConfig) -> Protocol {
+    let protocol = match config.image_protocol.as_str() {
+        "kitty" => Protocol::Kitty,
+        "sixel" => Protocol::Sixel,
+        "halfblocks" => Protocol::HalfBlocks,
+        other => {
+            if other != "auto" {
+                log::warn!("Unknown image_protocol {:?}, detecting one instead", other);
+            }
+            if config.kitty_graphics && kitty_supported() {
+                Protocol::Kitty
+            } else if sixel_supported() {
+                Protocol::Sixel
+            } else {
+                Protocol::HalfBlocks
+            }
+        }
+    };
+    log::info!("Image previews use {:?}", protocol);
+    protocol
+}
+
+impl Graphics {
+    pub fn new(protocol: Protocol) -> Self {
+        Self {
+            protocol,
+            cell_size: cell_size(),
+            images: HashMap::new(),
+            requested: HashSet::new(),
+            missing: Vec::new(),
+            image_ids: HashMap::new(),
+            flushes: 0,
+            sixels: HashMap::new(),
+            next_image_id: 1,
+            wanted: Vec::new(),
+            placed: Vec::new(),
+            reset: false,
+        }
+    }
+
+    /// Start collecting placements for a new frame
+    pub fn begin_frame(&mut self) {
+        self.wanted.clear();
+    }
+
+    /// Size in cells of a message's preview if it fits in `max_cols`
+    ///
+    /// Previews that aren't loaded yet are remembered, see `take_missing`.
+    pub fn preview_size(&mut self, message_id: &str, max_cols: u16) -> Option<(u16, u16)> {
+        let image = self.image(&ImageKey::Preview(message_id.to_string()))?;
+        let (cols, rows) = match self.protocol {
+            // Native size, the thumbnail is already capped
+            Protocol::Kitty | Protocol::Sixel => {
+                let (cell_width, cell_height) = self.cell_size;
+                let cols = image.width().div_ceil(cell_width as u32).max(1) as u16;
+                let rows = image.height().div_ceil(cell_height as u32).max(1) as u16;
+                (cols, rows)
+            }
+            // Low resolution, so always use the full preview box
+            Protocol::HalfBlocks => image_view::fit(image.width(), image.height(), MAX_COLS, MAX_ROWS),
+        };
+        (cols <= max_cols).then_some((cols, rows))
+    }
+
+    /// Whether a chat's avatar can be drawn, only with the Kitty protocol
+    ///
+    /// Avatars that aren't loaded yet are remembered, see `take_missing`.
+    pub fn has_avatar(&mut self, chat_id: &str) -> bool {
+        self.protocol == Protocol::Kitty && self.image(&ImageKey::Avatar(chat_id.to_string())).is_some()
+    }
+
+    /// A loaded image, or None after noting it as missing
+    fn image(&mut self, key: &ImageKey) -> Option<Arc<RgbaImage>> {
+        match self.images.get(key) {
+            Some(image) => image.clone(),
+            None => {
+                if !self.requested.contains(key) && !self.missing.contains(key) {
+                    self.missing.push(key.clone());
+                }
+                None
+            }
+        }
+    }
+
+    /// Draw an image over `area`: into `buf` now, or once the frame is flushed
+    pub fn draw(&mut self, buf: &mut Buffer, key: ImageKey, area: Rect) {
+        match self.protocol {
+            Protocol::Kitty => {
+                self.wanted.push(Placement { key, area });
+            }
+            Protocol::Sixel => {
+                // ratatui leaves these cells alone, so the image isn't painted over;
+                // once they stop being skipped ratatui rewrites them, erasing it
+                for y in area.top()..area.bottom() {
+                    for x in area.left()..area.right() {
+                        if let Some(cell) = buf.cell_mut((x, y)) {
+                            cell.set_skip(true);
+                        }
+                    }
+                }
+                self.wanted.push(Placement { key, area });
+            }
+            Protocol::HalfBlocks => {
+                if let Some(Some(image)) = self.images.get(&key) {
+                    ImageView::new(image).render(area, buf);
+                }
+            }
+        }
+    }
+
+    /// Images to fetch, marked as requested so they are only asked for once
+    pub fn take_missing(&mut self) -> Vec<ImageKey> {
+        let missing = std::mem::take(&mut self.missing);
+        self.requested.extend(missing.iter().cloned());
+        missing
+    }
+
+    /// Store a decoded image, or None to stop asking for it
+    ///
+    /// Only the first image for a key counts: what's on screen is never swapped out.
+    pub fn set_image(&mut self, key: ImageKey, image: Option<Arc<RgbaImage>>) {
+        self.requested.remove(&key);
+        if !matches!(self.images.get(&key), Some(Some(_))) {
+            self.images.insert(key, image);
+        }
+    }
+
+    /// Largest thumbnail worth decoding, in pixels
+    pub fn max_preview_px(&self) -> (u32, u32) {
+        let (cell_width, cell_height) = self.cell_size;
+        (MAX_COLS as u32 * cell_width as u32, MAX_ROWS as u32 * cell_height as u32)
+    }
+
+    /// The terminal was resized: cell size may have changed and the screen was cleared
+    pub fn resize(&mut self) {
+        self.cell_size = cell_size();
+        self.reset = true;
+    }
+
+    /// Bring the placements on screen in line with the frame just rendered
+    pub fn flush(&mut self, out: &mut impl Write) -> Result<()> {
+        match self.protocol {
+            Protocol::Kitty => self.flush_kitty(out),
+            Protocol::Sixel => self.flush_sixel(out),
+            Protocol::HalfBlocks => Ok(()),
+        }
+    }
+
+    fn flush_kitty(&mut self, out: &mut impl Write) -> Result<()> {
+        if std::mem::take(&mut self.reset) {
+            kitty::delete_all_placements(out)?;
+            self.placed.clear();
+        }
+
+        // Previews scrolled out of view or from another chat
+        for old in &self.placed {
+            if !self.wanted.iter().any(|p| p.key == old.key) {
+                kitty::delete_placement(out, self.image_ids[&old.key].0)?;
+            }
+        }
+
+        self.flushes += 1;
+        for placement in &self.wanted {
+            if let Some((_, last_shown)) = self.image_ids.get_mut(&placement.key) {
+                *last_shown = self.flushes;
+            }
+            if self.placed.contains(placement) {
+                continue;
+            }
+            let Some(Some(image)) = self.images.get(&placement.key) else {
+                continue;
+            };
+
+            let id = match self.image_ids.get(&placement.key) {
+                Some(&(id, _)) => id,
+                None => {
+                    let id = self.next_image_id;
+                    self.next_image_id += 1;
+                    kitty::transmit(out, id, image)?;
+                    self.image_ids.insert(placement.key.clone(), (id, self.flushes));
+                    id
+                }
+            };
+            kitty::place(out, id, placement.area)?;
+        }
+
+        self.placed = self.wanted.clone();
+        self.evict_kitty_images(out)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Free the least recently shown images beyond `MAX_KITTY_IMAGES`, never one on screen
+    fn evict_kitty_images(&mut self, out: &mut impl Write) -> Result<()> {
+        let excess = self.image_ids.len().saturating_sub(MAX_KITTY_IMAGES);
+        if excess == 0 {
+            return Ok(());
+        }
+
+        let mut idle: Vec<(u64, ImageKey)> = self.image_ids.iter()
+            .filter(|(key, _)| !self.placed.iter().any(|p| p.key == **key))
+            .map(|(key, &(_, last_shown))| (last_shown, key.clone()))
+            .collect();
+        idle.sort_by_key(|(last_shown, _)| *last_shown);
+        for (_, key) in idle.into_iter().take(excess) {
+            if let Some((id, _)) = self.image_ids.remove(&key) {
+                kitty::delete_image(out, id)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn flush_sixel(&mut self, out: &mut impl Write) -> Result<()> {
+        if std::mem::take(&mut self.reset) {
+            self.placed.clear();
+        }
+
+        // Sixels can't be moved or removed; old ones vanish as ratatui repaints their cells
+        for placement in &self.wanted {
+            if self.placed.contains(placement) {
+                continue;
+            }
+            let Some(Some(image)) = self.images.get(&placement.key) else {
+                continue;
+            };
+
+            let data = self.sixels.entry(placement.key.clone())
+                .or_insert_with(|| sixel::encode(image));
+            sixel::draw(out, placement.area, data)?;
+        }
+
+        self.placed = self.wanted.clone();
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Remove all images from the terminal, used on exit
+    pub fn clear(&mut self, out: &mut impl Write) -> Result<()> {
+        if self.protocol == Protocol::Kitty && !self.image_ids.is_empty() {
+            kitty::delete_all(out)?;
+            out.flush()?;
+        }
+        self.image_ids.clear();
+        self.placed.clear();
+        Ok(())
+    }
+}
+
+/// Decode an image file into a thumbnail no larger than `max` pixels
+pub fn load_preview(path: &Path, max: (u32, u32)) -> Result<RgbaImage> {
+    let image = image::ImageReader::open(path)
+        .with_context(|| format!("Cannot open {}", path.display()))?
+        .with_guessed_format()?
+        .decode()
+        .with_context(|| format!("Cannot decode {}", path.display()))?;
+
+    // Small images and stickers keep their size
+    if image.width() <= max.0 && image.height() <= max.1 {
+        return Ok(image.into_rgba8());
+    }
+    Ok(image.thumbnail(max.0, max.1).into_rgba8())
+}
+
+/// Decode a profile picture into a small square thumbnail
+pub fn load_avatar(data: &[u8]) -> Result<RgbaImage> {
+    let image = image::load_from_memory(data).context("Cannot decode profile picture")?;
+
+    // Crop to the centered square so the avatar isn't stretched
+    let side = image.width().min(image.height());
+    let square = image.crop_imm((image.width() - side) / 2, (image.height() - side) / 2, side, side);
+    Ok(square.thumbnail(AVATAR_PX, AVATAR_PX).into_rgba8())
+}
+
+/// Whether the terminal understands the Kitty graphics protocol
+fn kitty_supported() -> bool {
+    // Multiplexers swallow the escapes unless set up for passthrough
+    if std::env::var_os("TMUX").is_some() {
+        return false;
+    }
+
+    std::env::var_os("KITTY_WINDOW_ID").is_some()
+        || std::env::var("TERM").is_ok_and(|term| term.contains("kitty") || term.contains("ghostty"))
+        || std::env::var("TERM_PROGRAM").is_ok_and(|program| matches!(program.as_str(), "WezTerm" | "ghostty"))
+}
+
+/// Whether the terminal draws Sixel graphics, from its name or its primary device attributes
+fn sixel_supported() -> bool {
+    if std::env::var_os("TMUX").is_some() {
+        return false;
+    }
+    if let Some(known) = known_sixel_support() {
+        return known;
+    }
+
+    // Attribute 4 in a `CSI ? 62 ; 4 ; ... c` reply means Sixel
+    let Some(reply) = query_terminal("\x1b[c", b'c', Duration::from_millis(200)) else {
+        log::debug!("No reply to the device attributes query");
+        return false;
+    };
+    reply.rfind("\x1b[?")
+        .and_then(|start| reply[start + 3..].strip_suffix('c'))
+        .is_some_and(|attributes| attributes.split(';').any(|a| a == "4"))
+}
+
+/// Sixel support of terminals that say who they are, None if we have to ask
+fn known_sixel_support() -> Option<bool> {
+    let program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+    let term = std::env::var("TERM").unwrap_or_default();
+    match program.as_str() {
+        "WezTerm" | "iTerm.app" => return Some(true),
+        "Apple_Terminal" | "vscode" | "ghostty" => return Some(false),
+        _ => {}
+    }
+    if ["foot", "mlterm", "contour"].iter().any(|name| term.starts_with(name)) {
+        Some(true)
+    } else if term == "linux" || ["kitty", "alacritty", "ghostty"].iter().any(|name| term.contains(name)) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Write `query` and read the reply up to `terminator`, waiting at most `timeout`
+///
+/// Only safe before crossterm starts reading input, or it would race for the reply.
+/// A reply that comes in late is read and dropped before giving up.
+#[cfg(unix)]
+fn query_terminal(query: &str, terminator: u8, timeout: Duration) -> Option<String> {
+    use crossterm::terminal::{disable_raw_mode, enable_raw_mode, is_raw_mode_enabled};
+
+    // The reply must not echo or wait for a newline
+    let was_raw = is_raw_mode_enabled().ok()?;
+    if !was_raw {
+        enable_raw_mode().ok()?;
+    }
+
+    let mut stdout = std::io::stdout();
+    let reply = stdout.write_all(query.as_bytes())
+        .and_then(|_| stdout.flush())
+        .ok()
+        .and_then(|_| read_reply(terminator, timeout));
+    if reply.is_none() {
+        read_reply(terminator, LATE_REPLY_GRACE);
+    }
+
+    if !was_raw {
+        let _ = disable_raw_mode();
+    }
+    reply
+}
+
+/// Read stdin up to `terminator`, None if it doesn't come within `timeout`
+#[cfg(unix)]
+fn read_reply(terminator: u8, timeout: Duration) -> Option<String> {
+    use std::os::fd::AsRawFd;
+
+    let fd = std::io::stdin().as_raw_fd();
+    let deadline = std::time::Instant::now() + timeout;
+    let mut reply = Vec::new();
+    loop {
+        let remaining = deadline.checked_duration_since(std::time::Instant::now())?;
+        let mut pollfd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+        // SAFETY: a single valid pollfd
+        let ready = unsafe { libc::poll(&mut pollfd, 1, remaining.as_millis() as libc::c_int) };
+        if ready <= 0 {
+            return None;
+        }
+
+        // One byte at a time, so nothing typed after the reply is swallowed
+        let mut byte = 0u8;
+        // SAFETY: reads one byte into a valid buffer
+        if unsafe { libc::read(fd, (&mut byte as *mut u8).cast(), 1) } != 1 {
+            return None;
+        }
+        reply.push(byte);
+        if byte == terminator {
+            return String::from_utf8(reply).ok();
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn query_terminal(_query: &str, _terminator: u8, _timeout: Duration) -> Option<String> {
+    None
+}
+
+/// Pixel size of a cell, from the terminal's reported window size
+fn cell_size() -> (u16, u16) {
+    match crossterm::terminal::window_size() {
+        Ok(size) if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 => {
+            ((size.width / size.columns).max(1), (size.height / size.rows).max(1))
+        }
+        _ => DEFAULT_CELL_SIZE,
+    }
+}
@@ -0,0 +1,110 @@
+//! Sixel graphics, as drawn by xterm, foot, WezTerm and mlterm
+//!
+//! Colors are mapped onto a fixed 6x6x6 cube, which every Sixel terminal
+//! can hold in its palette registers.
+
+use image::RgbaImage;
+use ratatui::layout::Rect;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+/// Levels per channel in the color cube
+const LEVELS: u32 = 6;
+
+/// Pixels with less opacity than this are left unpainted
+const ALPHA_THRESHOLD: u8 = 128;
+
+/// Encode RGBA pixels as a complete Sixel sequence
+pub fn encode(image: &RgbaImage) -> String {
+    let (width, height) = image.dimensions();
+    let colors: Vec<Option<usize>> = image.pixels()
+        .map(|p| (p[3] >= ALPHA_THRESHOLD).then(|| cube_index(p[0], p[1], p[2])))
+        .collect();
+
+    // P2=1: unpainted pixels stay transparent. Raster attributes give square pixels and the exact size
+    let mut out = format!("\x1bP0;1;0q\"1;1;{};{}", width, height);
+
+    let mut used = vec![false; (LEVELS * LEVELS * LEVELS) as usize];
+    for &color in colors.iter().flatten() {
+        used[color] = true;
+    }
+    for (color, _) in used.iter().enumerate().filter(|(_, used)| **used) {
+        let (r, g, b) = cube_rgb(color);
+        let _ = write!(out, "#{};2;{};{};{}", color, r, g, b);
+    }
+
+    // Six pixel rows per band, one pass over the band per color
+    for top in (0..height).step_by(6) {
+        let rows = (height - top).min(6);
+        let mut band_colors: Vec<usize> = (0..rows)
+            .flat_map(|k| (0..width).map(move |x| ((top + k) * width + x) as usize))
+            .filter_map(|i| colors[i])
+            .collect();
+        band_colors.sort_unstable();
+        band_colors.dedup();
+
+        for (n, &color) in band_colors.iter().enumerate() {
+            if n > 0 {
+                out.push('$');
+            }
+            let _ = write!(out, "#{}", color);
+
+            let mut run: Option<(char, u32)> = None;
+            for x in 0..width {
+                let bits = (0..rows)
+                    .filter(|&k| colors[((top + k) * width + x) as usize] == Some(color))
+                    .fold(0u8, |bits, k| bits | (1 << k));
+                let ch = char::from(63 + bits);
+
+                run = match run {
+                    Some((prev, len)) if prev == ch => Some((prev, len + 1)),
+                    Some((prev, len)) => {
+                        push_run(&mut out, prev, len);
+                        Some((ch, 1))
+                    }
+                    None => Some((ch, 1)),
+                };
+            }
+            // Nothing painted at the end of the row needs no bytes
+            if let Some((ch, len)) = run.filter(|(ch, _)| *ch != '?') {
+                push_run(&mut out, ch, len);
+            }
+        }
+        out.push('-');
+    }
+
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Blank `area` and draw an encoded image at its top-left corner
+pub fn draw(out: &mut impl Write, area: Rect, data: &str) -> io::Result<()> {
+    // Save and restore the cursor (and colors) so ratatui's idea of them stays right
+    write!(out, "\x1b7\x1b[0m")?;
+    for row in 0..area.height {
+        write!(out, "\x1b[{};{}H\x1b[{}X", area.y + row + 1, area.x + 1, area.width)?;
+    }
+    write!(out, "\x1b[{};{}H{}\x1b8", area.y + 1, area.x + 1, data)
+}
+
+/// `len` repetitions of a sixel character, run-length encoded when shorter
+fn push_run(out: &mut String, ch: char, len: u32) {
+    if len >= 4 {
+        let _ = write!(out, "!{}{}", len, ch);
+    } else {
+        out.extend(std::iter::repeat_n(ch, len as usize));
+    }
+}
+
+/// Nearest color in the cube
+fn cube_index(r: u8, g: u8, b: u8) -> usize {
+    let level = |c: u8| (c as u32 * (LEVELS - 1) + 127) / 255;
+    (level(r) * LEVELS * LEVELS + level(g) * LEVELS + level(b)) as usize
+}
+
+/// A cube color as the 0-100 percentages Sixel palettes use
+fn cube_rgb(index: usize) -> (u32, u32, u32) {
+    let index = index as u32;
+    let percent = |level: u32| level * 100 / (LEVELS - 1);
+    (percent(index / (LEVELS * LEVELS)), percent(index / LEVELS % LEVELS), percent(index % LEVELS))
+}
@@ -1,6 +1,12 @@
 mod app;
+mod clock;
 mod components;
+mod format;
+mod fuzzy;
+mod graphics;
 mod theme;
+mod wrap;
 
 pub use app::App;
+pub use graphics::{detect_protocol, Protocol as ImageProtocol};
 pub use theme::Theme;
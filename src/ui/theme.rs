@@ -1,33 +1,72 @@
+use anyhow::{anyhow, bail, Context, Result};
 use ratatui::style::Color;
+use serde::{Deserialize, Deserializer};
+use std::path::Path;
+use std::str::FromStr;
 
-/// Color themuse ratatui::style::Color;
-
-#[derive(Debug, Clone)]
+/// Color theme, built in or loaded from `<config dir>/themes/<name>.toml`
+///
+/// Theme files set every color below by name ("green", "light-blue"),
+/// 256-color index ("42"), hex ("#25d366") or "reset" for the terminal default.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Theme {
+    #[serde(deserialize_with = "color")]
     pub system: Color,
-    
+
     // Borders
+    #[serde(deserialize_with = "color")]
     pub border: Color,           // Unfocused: transparent white
+    #[serde(deserialize_with = "color")]
     pub border_focused: Color,   // Focused: brighter/colored
-    
+
     // Message colors
+    #[serde(deserialize_with = "color")]
     pub me: Color,
+    #[serde(deserialize_with = "color")]
     pub other: Color,
+    #[serde(deserialize_with = "color")]
     pub highlight: Color,
+    #[serde(deserialize_with = "color")]
     pub primary: Color,
 }
 
 impl Theme {
+    /// A built-in theme, or a user theme from `themes_dir`
+    pub fn load(name: &str, themes_dir: &Path) -> Result<Self> {
+        match name {
+            "dark" => return Ok(Self::dark()),
+            "light" => return Ok(Self::light()),
+            "terminal" => return Ok(Self::terminal()),
+            _ => {}
+        }
+
+        let path = themes_dir.join(format!("{}.toml", name));
+        if !path.exists() {
+            bail!("No theme named '{}': not built in (dark, light, terminal) and {} doesn't exist", name, path.display());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read theme {}", path.display()))?;
+        // toml's own message spans several lines, too much for the status bar
+        toml::from_str(&content).map_err(|e| {
+            let line = e.span().map(|span| content[..span.start].lines().count().max(1));
+            match line {
+                Some(line) => anyhow!("Invalid theme {} (line {}): {}", path.display(), line, e.message()),
+                None => anyhow!("Invalid theme {}: {}", path.display(), e.message()),
+            }
+        })
+    }
+
     pub fn terminal() -> Self {
         Self {
             system: Color::DarkGray,
-            
+
             // Transparent white border when unfocused
             border: Color::Reset,
-            
+
             // Green when focused
             border_focused: Color::Green,
-            
+
             // Message colors use terminal palette
             me: Color::White,
             other: Color::Green,
@@ -35,4 +74,37 @@ impl Theme {
             primary: Color::Blue,
         }
     }
+
+    /// WhatsApp's dark mode colors, for dark terminal backgrounds
+    pub fn dark() -> Self {
+        Self {
+            system: Color::Rgb(134, 150, 160),
+            border: Color::Rgb(52, 61, 67),
+            border_focused: Color::Rgb(37, 211, 102),
+            me: Color::Rgb(217, 253, 211),
+            other: Color::Rgb(83, 189, 235),
+            highlight: Color::Rgb(255, 210, 121),
+            primary: Color::Rgb(0, 168, 132),
+        }
+    }
+
+    /// WhatsApp's light mode colors, for light terminal backgrounds
+    pub fn light() -> Self {
+        Self {
+            system: Color::Rgb(102, 119, 129),
+            border: Color::Rgb(209, 215, 219),
+            border_focused: Color::Rgb(0, 128, 105),
+            me: Color::Rgb(17, 27, 33),
+            other: Color::Rgb(2, 126, 181),
+            highlight: Color::Rgb(197, 138, 0),
+            primary: Color::Rgb(83, 189, 235),
+        }
+    }
+}
+
+/// A color in any notation ratatui understands
+fn color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    Color::from_str(&value)
+        .map_err(|_| serde::de::Error::custom(format!("unknown color '{}'", value)))
 }
@@ -0,0 +1,98 @@
+//! Word wrapping of styled lines into rows of a fixed width
+//!
+//! The message view wraps lines itself instead of leaving it to `Paragraph`,
+//! so it knows how many rows every message takes and can reuse them between
+//! frames. Widths come from unicode-width, one char at a time like the
+//! composer's wrapping.
+
+use ratatui::style::Style;
+use ratatui::text::{Line, Span};
+use unicode_width::UnicodeWidthChar;
+
+/// Break `line` into rows of at most `width` columns
+///
+/// Rows break after the last space that fits, or mid-word when a word alone
+/// is wider than a row; a space that would overflow a row is dropped. Rows
+/// keep the line's style and alignment.
+pub fn wrap_line(line: &Line<'_>, width: u16) -> Vec<Line<'static>> {
+    let width = width.max(1) as usize;
+    let chars: Vec<(char, Style)> = line.spans.iter()
+        .flat_map(|span| span.content.chars().map(move |c| (c, span.style)))
+        .collect();
+    let char_width = |c: char| c.width().unwrap_or(0);
+
+    let mut rows = Vec::new();
+    let (mut start, mut used) = (0, 0);
+    let mut after_space = None;  // Where the current row can break, right after a space
+    for (i, &(c, _)) in chars.iter().enumerate() {
+        if used + char_width(c) > width && i > start {
+            if c == ' ' {
+                rows.push(row(line, &chars[start..i]));
+                (start, used, after_space) = (i + 1, 0, None);
+                continue;
+            }
+            let end = after_space.unwrap_or(i);
+            rows.push(row(line, &chars[start..end]));
+            start = end;
+            used = chars[start..i].iter().map(|&(c, _)| char_width(c)).sum();
+            after_space = None;
+        }
+        used += char_width(c);
+        if c == ' ' {
+            after_space = Some(i + 1);
+        }
+    }
+    if start < chars.len() || rows.is_empty() {
+        rows.push(row(line, &chars[start..]));
+    }
+    rows
+}
+
+/// One row: runs of chars with the same style become a span each
+fn row(line: &Line<'_>, chars: &[(char, Style)]) -> Line<'static> {
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    for &(c, style) in chars {
+        match spans.last_mut() {
+            Some(span) if span.style == style => span.content.to_mut().push(c),
+            _ => spans.push(Span::styled(c.to_string(), style)),
+        }
+    }
+    Line { spans, style: line.style, alignment: line.alignment }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::style::Color;
+
+    fn texts(line: &Line<'_>, width: u16) -> Vec<String> {
+        wrap_line(line, width).iter().map(|row| row.to_string()).collect()
+    }
+
+    #[test]
+    fn breaks_at_spaces() {
+        let line = Line::from("see you at the station");
+        assert_eq!(texts(&line, 12), ["see you at ", "the station"]);
+        assert_eq!(texts(&line, 22), ["see you at the station"]);
+        // A space that doesn't fit is dropped rather than starting the next row
+        assert_eq!(texts(&line, 10), ["see you at", "the ", "station"]);
+    }
+
+    #[test]
+    fn breaks_long_words_and_counts_wide_chars() {
+        assert_eq!(texts(&Line::from("abcdefgh"), 3), ["abc", "def", "gh"]);
+        assert_eq!(texts(&Line::from("日本語です"), 4), ["日本", "語で", "す"]);
+        assert_eq!(texts(&Line::from(""), 10), [""]);
+    }
+
+    #[test]
+    fn keeps_styles_and_alignment() {
+        let red = Style::default().fg(Color::Red);
+        let line = Line::from(vec![Span::raw("hello "), Span::styled("red world", red)]).centered();
+        let rows = wrap_line(&line, 10);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].spans, [Span::raw("hello "), Span::styled("red ", red)]);
+        assert_eq!(rows[1].spans, [Span::styled("world", red)]);
+        assert!(rows.iter().all(|row| row.alignment == line.alignment));
+    }
+}
@@ -0,0 +1,53 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+use super::types::*;
+
+/// Transport the UI talks to
+///
+/// `WhatsAppClient` implements this on top of the Node.js service, while
+/// `MemoryBackend` keeps everything in process for demos and tests. Events
+/// flow through the backend's event channel; the app also posts its own
+/// internal events (chats loaded, errors, ...) on the same channel.
+#[async_trait]
+pub trait WhatsAppBackend: Send + Sync {
+    /// Sender half of the event stream consumed by the main loop
+    fn event_sender(&self) -> mpsc::Sender<WhatsAppEvent>;
+
+    /// Get all chats
+    async fn get_chats(&self) -> Result<Vec<Chat>>;
+
+    /// Get the latest `limit` messages for a chat, oldest first
+    async fn get_messages(&self, chat_id: &str, limit: usize) -> Result<Vec<Message>>;
+
+    /// Send a text message, optionally as a reply quoting `quoted_id`
+    ///
+    /// `client_id` stays the same across retries of one message, so a retry after a
+    /// lost response doesn't send it twice.
+    async fn send_message(&self, chat_id: &str, text: &str, quoted_id: Option<&str>, client_id: &str) -> Result<()>;
+
+    /// Send a local file with an optional caption, reporting `UploadProgress` events
+    async fn send_media(&self, chat_id: &str, upload: &MediaUpload, caption: &str, quoted_id: Option<&str>) -> Result<()>;
+
+    /// React to a message with `emoji`; an empty string removes our reaction
+    async fn react(&self, message_id: &str, emoji: &str) -> Result<()>;
+
+    /// Replace the text of a message we sent
+    async fn edit_message(&self, message_id: &str, text: &str) -> Result<()>;
+
+    /// Delete a message, for everyone (our own messages only) or just for us
+    async fn delete_message(&self, message_id: &str, for_everyone: bool) -> Result<()>;
+
+    /// Forward messages, in order, to each of `chat_ids`
+    async fn forward_messages(&self, message_ids: &[String], chat_ids: &[String]) -> Result<()>;
+
+    /// Download the media attached to a message, reporting `DownloadProgress` events
+    async fn download_media(&self, message_id: &str) -> Result<MediaDownload>;
+
+    /// Profile picture of a chat, None if it has none or it's hidden from us
+    async fn get_profile_picture(&self, chat_id: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Messages in any chat containing `query`, searched on the server, newest first
+    async fn search_messages(&self, query: &str, limit: usize) -> Result<Vec<Message>>;
+}
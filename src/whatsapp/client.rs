@@ -1,38 +1,141 @@
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use futures_util::stream::SplitStream;
 use futures_util::{SinkExt, StreamExt};
+use rand::Rng;
 use serde_json::json;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpStream;
 use tokio::sync::{mpsc, RwLock};
-use tokio_tungstenite::{connect_async, tungstenite::Message as WsMessage};
+use tokio_tungstenite::{connect_async, tungstenite::Message as WsMessage, MaybeTlsStream, WebSocketStream};
 use uuid::Uuid;
 
+use super::backend::WhatsAppBackend;
 use super::types::*;
 
 type PendingRequests = Arc<RwLock<HashMap<String, mpsc::Sender<WhatsAppResponse>>>>;
 
+/// Write half of the current connection, swapped by the supervisor on reconnect
+type WriteChannel = Arc<RwLock<Option<mpsc::Sender<WsMessage>>>>;
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Read half of a connection whose write half is already in use
+type WsReader = SplitStream<WsStream>;
+
+/// First reconnect delay, doubled on every failed attempt
+const RECONNECT_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Upper bound for the reconnect delay
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Raw bytes per media chunk request, keeps each WebSocket frame small
+const MEDIA_CHUNK_SIZE: usize = 256 * 1024;
+
 /// WhatsApp client that communicates with Node.js service via WebSocket
+///
+/// The connection is supervised: when the service goes away the client keeps
+/// redialing with exponential backoff, and every clone transparently starts
+/// using the new connection once it is back.
 #[derive(Clone)]
 pub struct WhatsAppClient {
-    write_tx: mpsc::Sender<WsMessage>,
+    write_tx: WriteChannel,
     pending: PendingRequests,
+    event_tx: mpsc::Sender<WhatsAppEvent>,
 }
 
 impl WhatsAppClient {
     /// Connect to WhatsApp service
     pub async fn connect(url: &str, event_tx: mpsc::Sender<WhatsAppEvent>) -> Result<Self> {
+        let ws_stream = Self::dial(url).await?;
+        let client = Self::new(event_tx);
+        // Requests work as soon as this returns
+        let reader = client.attach(ws_stream).await;
+        client.supervise_in_background(url, Some(reader));
+        Ok(client)
+    }
+
+    /// Start disconnected and keep redialing in the background
+    ///
+    /// Used when the service is down at startup so cached history stays browsable.
+    pub fn connect_in_background(url: &str, event_tx: mpsc::Sender<WhatsAppEvent>) -> Self {
+        let client = Self::new(event_tx);
+        client.supervise_in_background(url, None);
+        client
+    }
+
+    fn new(event_tx: mpsc::Sender<WhatsAppEvent>) -> Self {
+        Self {
+            write_tx: Arc::new(RwLock::new(None)),
+            pending: Arc::new(RwLock::new(HashMap::new())),
+            event_tx,
+        }
+    }
+
+    /// Spawn the supervisor that owns the connection and redials when it drops
+    fn supervise_in_background(&self, url: &str, reader: Option<WsReader>) {
+        tokio::spawn(self.clone().supervise(url.to_string(), reader));
+    }
+
+    async fn dial(url: &str) -> Result<WsStream> {
         let (ws_stream, _) = connect_async(url)
             .await
             .context("Failed to connect to WhatsApp service")?;
-        
+
         log::info!("Connected to WhatsApp service");
-        
-        let (mut write, mut read) = ws_stream.split();
+        Ok(ws_stream)
+    }
+
+    /// Run connections until the app goes away, reconnecting in between
+    async fn supervise(self, url: String, mut reader: Option<WsReader>) {
+        let event_tx = self.event_tx.clone();
+        loop {
+            if let Some(read) = reader.take() {
+                self.run_connection(read, &event_tx).await;
+
+                // Drop the dead write channel and fail everything still waiting on it
+                *self.write_tx.write().await = None;
+                self.fail_pending().await;
+            }
+
+            if event_tx.send(WhatsAppEvent::Disconnected).await.is_err() {
+                break; // App is gone, nobody to reconnect for
+            }
+
+            let mut attempt = 0;
+            let stream = loop {
+                attempt += 1;
+                if event_tx.send(WhatsAppEvent::Reconnecting(attempt)).await.is_err() {
+                    return;
+                }
+
+                let delay = reconnect_delay(attempt);
+                log::info!("Reconnecting to {} in {:?} (attempt {})", url, delay, attempt);
+                tokio::time::sleep(delay).await;
+
+                match Self::dial(&url).await {
+                    Ok(stream) => break stream,
+                    Err(e) => log::warn!("Reconnect attempt {} failed: {}", attempt, e),
+                }
+            };
+            reader = Some(self.attach(stream).await);
+
+            if event_tx.send(WhatsAppEvent::Reconnected).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    /// Make a new connection the one requests go out on, for every clone
+    async fn attach(&self, ws_stream: WsStream) -> WsReader {
+        let (mut write, read) = ws_stream.split();
         let (write_tx, mut write_rx) = mpsc::channel::<WsMessage>(100);
-        
-        let pending: PendingRequests = Arc::new(RwLock::new(HashMap::new()));
-        let pending_clone = Arc::clone(&pending);
-        
+
+        *self.write_tx.write().await = Some(write_tx);
+
         // Spawn task to write messages
         tokio::spawn(async move {
             while let Some(msg) = write_rx.recv().await {
@@ -42,141 +145,313 @@ impl WhatsAppClient {
                 }
             }
         });
-        
-        // Spawn task to read messages
-        tokio::spawn(async move {
-            while let Some(msg) = read.next().await {
-                match msg {
-                    Ok(WsMessage::Text(text)) => {
-                        // Try to parse as event first
-                        if let Ok(event) = serde_json::from_str::<WhatsAppEvent>(&text) {
-                            if let Err(e) = event_tx.send(event).await {
-                                log::error!("Failed to send event to app: {}", e);
-                            }
-                        }
-                        // Otherwise try as response
-                        else if let Ok(response) = serde_json::from_str::<WhatsAppResponse>(&text) {
-                            let pending = pending_clone.read().await;
-                            if let Some(tx) = pending.get(&response.id) {
-                                let _ = tx.send(response).await;
-                            }
-                        } else {
-                            log::warn!("Received unknown message format: {}", text);
+
+        read
+    }
+
+    /// Pump a single connection until it closes or errors
+    async fn run_connection(&self, mut read: WsReader, event_tx: &mpsc::Sender<WhatsAppEvent>) {
+        // Read messages until the connection ends
+        while let Some(msg) = read.next().await {
+            match msg {
+                Ok(WsMessage::Text(text)) => {
+                    // Try to parse as event first
+                    if let Ok(event) = serde_json::from_str::<WhatsAppEvent>(&text) {
+                        if let Err(e) = event_tx.send(event).await {
+                            log::error!("Failed to send event to app: {}", e);
                         }
                     }
-                    Ok(WsMessage::Close(_)) => {
-                        log::warn!("WhatsApp service closed connection");
-                        let _ = event_tx.send(WhatsAppEvent::Disconnected).await;
-                        break;
-                    }
-                    Err(e) => {
-                        log::error!("WebSocket error: {}", e);
-                        let _ = event_tx.send(WhatsAppEvent::Disconnected).await;
-                        break;
+                    // Otherwise try as response
+                    else if let Ok(response) = serde_json::from_str::<WhatsAppResponse>(&text) {
+                        let pending = self.pending.read().await;
+                        if let Some(tx) = pending.get(&response.id) {
+                            let _ = tx.send(response).await;
+                        }
+                    } else {
+                        log::warn!("Received unknown message format: {}", text);
                     }
-                    _ => {}
                 }
+                Ok(WsMessage::Close(_)) => {
+                    log::warn!("WhatsApp service closed connection");
+                    break;
+                }
+                Err(e) => {
+                    log::error!("WebSocket error: {}", e);
+                    break;
+                }
+                _ => {}
             }
-        });
-        
-        Ok(Self {
-            write_tx,
-            pending,
-        })
+        }
+    }
+
+    /// Fail every in-flight request now instead of letting it time out
+    ///
+    /// Dropping the response senders wakes each waiter with a closed channel.
+    async fn fail_pending(&self) {
+        self.pending.write().await.clear();
     }
-    
+
     /// Send a request and wait for response
     async fn request(&self, method: &str, params: serde_json::Value) -> Result<serde_json::Value> {
         self.request_with_timeout(method, params, 30).await
     }
-    
+
     /// Send a request with custom timeout
     async fn request_with_timeout(&self, method: &str, params: serde_json::Value, timeout_secs: u64) -> Result<serde_json::Value> {
+        let write_tx = self.write_tx.read().await.clone()
+            .ok_or_else(|| anyhow!("Not connected to WhatsApp service"))?;
+
         let id = Uuid::new_v4().to_string();
         let (response_tx, mut response_rx) = mpsc::channel(1);
-        
+
         // Register pending request
         {
             let mut pending = self.pending.write().await;
             pending.insert(id.clone(), response_tx);
         }
-        
+
         // Send request
         let req = WhatsAppRequest {
             id: id.clone(),
             method: method.to_string(),
             params,
         };
-        
+
         let msg = WsMessage::Text(serde_json::to_string(&req)?);
-        self.write_tx.send(msg).await
-            .context("Failed to send request")?;
-        
-        // Wait for response with custom timeout
-        let response = tokio::time::timeout(
-            tokio::time::Duration::from_secs(timeout_secs),
-            response_rx.recv()
-        )
-        .await
-        .context(format!("Request timed out after {}s", timeout_secs))?
-        .ok_or_else(|| anyhow!("Response channel closed"))?;
-        
+        let response = match write_tx.send(msg).await {
+            Ok(()) => {
+                // Wait for response with custom timeout
+                tokio::time::timeout(
+                    tokio::time::Duration::from_secs(timeout_secs),
+                    response_rx.recv()
+                )
+                .await
+            }
+            Err(e) => {
+                self.pending.write().await.remove(&id);
+                return Err(anyhow!(e).context("Failed to send request"));
+            }
+        };
+
         // Clean up pending request
         {
             let mut pending = self.pending.write().await;
             pending.remove(&id);
         }
-        
+
+        let response = response
+            .context(format!("Request timed out after {}s", timeout_secs))?
+            .ok_or_else(|| anyhow!("Connection to WhatsApp service lost"))?;
+
         // Check for error
         if let Some(error) = response.error {
-            return Err(anyhow!("WhatsApp service error: {}", error));
+            return Err(ServiceError(error).into());
         }
-        
+
         response.result.ok_or_else(|| anyhow!("No result in response"))
     }
-    
+}
+
+#[async_trait]
+impl WhatsAppBackend for WhatsAppClient {
+    fn event_sender(&self) -> mpsc::Sender<WhatsAppEvent> {
+        self.event_tx.clone()
+    }
+
     /// Get all chats (with longer timeout as this can be slow with many chats)
-    pub async fn get_chats(&self) -> Result<Vec<Chat>> {
+    async fn get_chats(&self) -> Result<Vec<Chat>> {
         let result = self.request_with_timeout("getChats", json!({}), 300).await?;
-        
+
         // Log the actual JSON for debugging
         log::debug!("getChats result: {}", serde_json::to_string_pretty(&result).unwrap_or_else(|_| "<invalid json>".to_string()));
-        
+
         serde_json::from_value(result.clone())
             .context(format!("Failed to parse chats. JSON: {}", serde_json::to_string(&result).unwrap_or_else(|_| "?".to_string())))
     }
-    
+
     /// Get messages for a chat (with longer timeout for large chats)
-    pub async fn get_messages(&self, chat_id: &str, limit: usize) -> Result<Vec<Message>> {
+    async fn get_messages(&self, chat_id: &str, limit: usize) -> Result<Vec<Message>> {
         let result = self.request_with_timeout("getMessages", json!({
             "chatId": chat_id,
             "limit": limit
         }), 120).await?;  // 120 second timeout for large chats
         serde_json::from_value(result).context("Failed to parse messages")
     }
-    
+
+    /// Search all chats on the server, including messages never loaded here
+    async fn search_messages(&self, query: &str, limit: usize) -> Result<Vec<Message>> {
+        let result = self.request_with_timeout("searchMessages", json!({
+            "query": query,
+            "limit": limit
+        }), 60).await?;
+        serde_json::from_value(result).context("Failed to parse search results")
+    }
+
     /// Send a message
-    pub async fn send_message(&self, chat_id: &str, text: &str) -> Result<()> {
+    async fn send_message(&self, chat_id: &str, text: &str, quoted_id: Option<&str>, client_id: &str) -> Result<()> {
         self.request("sendMessage", json!({
             "chatId": chat_id,
+            "text": text,
+            "quotedMessageId": quoted_id,
+            "clientId": client_id
+        })).await?;
+        Ok(())
+    }
+
+    /// Send media, streaming the file to the service in chunks
+    async fn send_media(&self, chat_id: &str, upload: &MediaUpload, caption: &str, quoted_id: Option<&str>) -> Result<()> {
+        let upload_id = Uuid::new_v4().to_string();
+        self.request("sendMediaStart", json!({
+            "uploadId": upload_id,
+            "chatId": chat_id,
+            "filename": upload.filename,
+            "mimetype": upload.mimetype,
+            "size": upload.size,
+            "caption": caption,
+            "quotedMessageId": quoted_id
+        })).await?;
+
+        let mut file = tokio::fs::File::open(&upload.path).await
+            .with_context(|| format!("Cannot open {}", upload.path.display()))?;
+        let mut buf = vec![0u8; MEDIA_CHUNK_SIZE];
+        let mut offset = 0u64;
+
+        use base64::Engine;
+        loop {
+            let read = file.read(&mut buf).await?;
+            if read == 0 {
+                break;
+            }
+
+            self.request("sendMediaChunk", json!({
+                "uploadId": upload_id,
+                "offset": offset,
+                "data": base64::engine::general_purpose::STANDARD.encode(&buf[..read])
+            })).await?;
+            offset += read as u64;
+
+            let _ = self.event_tx.send(WhatsAppEvent::UploadProgress(upload.filename.clone(), offset, upload.size)).await;
+        }
+
+        // WhatsApp itself can take a while to accept large files
+        self.request_with_timeout("sendMediaFinish", json!({
+            "uploadId": upload_id
+        }), 300).await?;
+        Ok(())
+    }
+
+    /// React to a message
+    async fn react(&self, message_id: &str, emoji: &str) -> Result<()> {
+        self.request("react", json!({
+            "messageId": message_id,
+            "emoji": emoji
+        })).await?;
+        Ok(())
+    }
+
+    /// Edit a sent message
+    async fn edit_message(&self, message_id: &str, text: &str) -> Result<()> {
+        self.request("editMessage", json!({
+            "messageId": message_id,
             "text": text
         })).await?;
         Ok(())
     }
-    
-    /// Download media
-    #[allow(dead_code)]
-    pub async fn download_media(&self, message_id: &str) -> Result<Vec<u8>> {
-        let result = self.request("downloadMedia", json!({
+
+    /// Delete a message
+    async fn delete_message(&self, message_id: &str, for_everyone: bool) -> Result<()> {
+        self.request("deleteMessage", json!({
+            "messageId": message_id,
+            "everyone": for_everyone
+        })).await?;
+        Ok(())
+    }
+
+    /// Forward messages to other chats
+    async fn forward_messages(&self, message_ids: &[String], chat_ids: &[String]) -> Result<()> {
+        // One send per message and chat on the service side
+        self.request_with_timeout("forwardMessages", json!({
+            "messageIds": message_ids,
+            "chatIds": chat_ids
+        }), 60).await?;
+        Ok(())
+    }
+
+    /// Download media, fetching it from the service in chunks
+    async fn download_media(&self, message_id: &str) -> Result<MediaDownload> {
+        // The service fetches the whole file from WhatsApp first, which can be slow
+        let info = self.request_with_timeout("downloadMedia", json!({
             "messageId": message_id
+        }), 300).await?;
+
+        let download_id = info["downloadId"].as_str()
+            .ok_or_else(|| anyhow!("Message has no downloadable media"))?;
+        let size = info["size"].as_u64().unwrap_or(0);
+        let mut data = Vec::with_capacity(size as usize);
+
+        use base64::Engine;
+        while (data.len() as u64) < size {
+            let chunk = self.request("downloadMediaChunk", json!({
+                "downloadId": download_id,
+                "offset": data.len(),
+                "length": MEDIA_CHUNK_SIZE
+            })).await?;
+
+            let base64_str = chunk["data"].as_str()
+                .ok_or_else(|| anyhow!("Media data not a string"))?;
+            let bytes = base64::engine::general_purpose::STANDARD.decode(base64_str)
+                .context("Failed to decode base64 media")?;
+            if bytes.is_empty() {
+                bail!("Media download ended early at {} of {} bytes", data.len(), size);
+            }
+            data.extend(bytes);
+
+            let _ = self.event_tx.send(WhatsAppEvent::DownloadProgress(message_id.to_string(), data.len() as u64, size)).await;
+        }
+
+        Ok(MediaDownload {
+            data,
+            mimetype: info["mimetype"].as_str().unwrap_or("application/octet-stream").to_string(),
+            filename: info["filename"].as_str().filter(|f| !f.is_empty()).map(str::to_string),
+        })
+    }
+
+    /// Fetch a chat's profile picture
+    async fn get_profile_picture(&self, chat_id: &str) -> Result<Option<Vec<u8>>> {
+        let result = self.request("getProfilePicture", json!({
+            "chatId": chat_id
         })).await?;
-        
-        // Expecting base64-encoded data
-        let base64_str = result.as_str()
-            .ok_or_else(|| anyhow!("Media data not a string"))?;
-        
+
+        let Some(base64_str) = result["data"].as_str() else {
+            return Ok(None);
+        };
         use base64::Engine;
-        base64::engine::general_purpose::STANDARD.decode(base64_str)
-            .context("Failed to decode base64 media")
+        let data = base64::engine::general_purpose::STANDARD.decode(base64_str)
+            .context("Failed to decode base64 profile picture")?;
+        Ok(Some(data))
+    }
+}
+
+/// Exponential backoff with jitter: half the capped delay is fixed, the other half random
+fn reconnect_delay(attempt: u32) -> Duration {
+    let exp = RECONNECT_BASE_DELAY.saturating_mul(1u32 << attempt.saturating_sub(1).min(16));
+    let capped = exp.min(RECONNECT_MAX_DELAY);
+    let half = capped / 2;
+    half + half.mul_f64(rand::thread_rng().gen::<f64>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reconnect_delay_doubles_up_to_the_cap() {
+        for attempt in 1..=20 {
+            let capped = (RECONNECT_BASE_DELAY * 2u32.pow(attempt.min(16) - 1)).min(RECONNECT_MAX_DELAY);
+            let delay = reconnect_delay(attempt);
+            assert!(delay >= capped / 2 && delay <= capped, "attempt {}: {:?} outside {:?}", attempt, delay, capped);
+        }
+        // No overflow however long the service stays away
+        assert!(reconnect_delay(u32::MAX) <= RECONNECT_MAX_DELAY);
     }
 }
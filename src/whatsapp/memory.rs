@@ -0,0 +1,385 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::{mpsc, RwLock};
+use uuid::Uuid;
+
+use super::backend::WhatsAppBackend;
+use super::types::*;
+
+#[derive(Default)]
+struct MemoryState {
+    chats: Vec<Chat>,
+    messages: HashMap<String, Vec<Message>>,  // chat_id -> messages, oldest first
+    media: HashMap<String, MediaDownload>,  // message_id -> attachment
+    avatars: HashMap<String, Vec<u8>>,  // chat_id -> profile picture
+    sent: HashSet<String>,  // Client ids of sent messages, so retries aren't sent twice
+}
+
+/// In-process backend that serves chats and messages from memory
+#[derive(Clone)]
+pub struct MemoryBackend {
+    event_tx: mpsc::Sender<WhatsAppEvent>,
+    state: Arc<RwLock<MemoryState>>,
+}
+
+impl MemoryBackend {
+    pub fn new(
+        chats: Vec<Chat>,
+        messages: Vec<Message>,
+        media: HashMap<String, MediaDownload>,
+        avatars: HashMap<String, Vec<u8>>,
+        event_tx: mpsc::Sender<WhatsAppEvent>,
+    ) -> Self {
+        let mut by_chat: HashMap<String, Vec<Message>> = HashMap::new();
+        for msg in messages {
+            by_chat.entry(msg.chat_id.clone()).or_default().push(msg);
+        }
+        for chat_messages in by_chat.values_mut() {
+            chat_messages.sort_by_key(|m| m.timestamp);
+        }
+
+        Self {
+            event_tx,
+            state: Arc::new(RwLock::new(MemoryState {
+                chats,
+                messages: by_chat,
+                media,
+                avatars,
+                sent: HashSet::new(),
+            })),
+        }
+    }
+
+    /// Backend pre-filled with a few sample conversations
+    pub fn demo(event_tx: mpsc::Sender<WhatsAppEvent>) -> Self {
+        let now = chrono::Utc::now().timestamp();
+
+        let chat = |id: &str, name: &str, is_group: bool, unread_count: u32, archived: bool, age: i64| Chat {
+            id: id.to_string(),
+            name: name.to_string(),
+            is_group,
+            unread_count,
+            archived,
+            timestamp: now - age,
+            last_message: None,
+        };
+        let message = |chat_id: &str, body: &str, from_me: bool, sender: Option<&str>, age: i64| Message {
+            id: Uuid::new_v4().to_string(),
+            chat_id: chat_id.to_string(),
+            body: body.to_string(),
+            timestamp: now - age,
+            from_me,
+            has_media: false,
+            media_type: None,
+            sender: sender.map(str::to_string),
+            ack: if from_me { AckStatus::Read } else { AckStatus::Pending },
+            quoted: None,
+            reactions: Vec::new(),
+            edited: false,
+            deleted: false,
+            forwarded: false,
+        };
+
+        let chats = vec![
+            chat("5511999990001@c.us", "Alice", false, 1, false, 60),
+            chat("120363000000001@g.us", "Weekend Hike", true, 0, false, 3_600),
+            chat("5511999990002@c.us", "Bob", false, 0, false, 86_400),
+            chat("5511999990003@c.us", "Old Project", false, 0, true, 2_592_000),
+        ];
+
+        let messages = vec![
+            message("5511999990001@c.us", "Hey! Are we still on for lunch?", false, Some("Alice"), 600),
+            message("5511999990001@c.us", "Yes, 12:30 at the usual place", true, None, 540),
+            message("5511999990001@c.us", "Perfect, see you there", false, Some("Alice"), 60),
+            message("120363000000001@g.us", "Trail map is in the drive folder", false, Some("Carol"), 7_200),
+            message("120363000000001@g.us", "Who's bringing snacks?", false, Some("Dave"), 3_700),
+            message("120363000000001@g.us", "I'll bring trail mix", true, None, 3_600),
+            message("5511999990002@c.us", "Can you review my PR?", false, Some("Bob"), 86_400),
+            message("5511999990003@c.us", "Project wrapped up, thanks all!", false, Some("Erin"), 2_592_000),
+        ];
+
+        // Alice liked the lunch confirmation; Carol forwarded the map and shared a photo
+        let mut messages = messages;
+        messages[3].forwarded = true;
+        let mut photo = message("120363000000001@g.us", "View from the summit last time", false, Some("Carol"), 7_100);
+        photo.has_media = true;
+        photo.media_type = Some("image".to_string());
+        let photo_id = photo.id.clone();
+        messages.push(photo);
+        messages[1].reactions.push(Reaction {
+            emoji: "👍".to_string(),
+            sender_id: "5511999990001@c.us".to_string(),
+            from_me: false,
+        });
+
+        let mut chats = chats;
+        for chat in &mut chats {
+            chat.last_message = messages.iter()
+                .filter(|m| m.chat_id == chat.id)
+                .max_by_key(|m| m.timestamp)
+                .map(|m| m.body.clone());
+        }
+
+        let media = HashMap::from([(photo_id, MediaDownload {
+            data: include_bytes!("../../fixtures/media/sunset.png").to_vec(),
+            mimetype: "image/png".to_string(),
+            filename: Some("sunset.png".to_string()),
+        })]);
+
+        // Bob and Old Project have no profile picture
+        let avatars = HashMap::from([
+            ("5511999990001@c.us".to_string(), include_bytes!("../../fixtures/media/alice.png").to_vec()),
+            ("120363000000001@g.us".to_string(), include_bytes!("../../fixtures/media/weekend-hike.png").to_vec()),
+        ]);
+
+        Self::new(chats, messages, media, avatars, event_tx)
+    }
+
+    /// Announce the backend as authenticated and ready, like the service does on connect
+    pub async fn start(&self) -> Result<()> {
+        self.event_tx.send(WhatsAppEvent::Authenticated).await?;
+        self.event_tx.send(WhatsAppEvent::Ready).await?;
+        Ok(())
+    }
+
+    /// Store a message we sent and echo it back like the service does for message_create
+    async fn store_own_message(&self, chat_id: &str, body: &str, media_type: Option<&str>, quoted_id: Option<&str>) -> Result<String> {
+        let mut msg = Message {
+            id: Uuid::new_v4().to_string(),
+            chat_id: chat_id.to_string(),
+            body: body.to_string(),
+            timestamp: chrono::Utc::now().timestamp(),
+            from_me: true,
+            has_media: media_type.is_some(),
+            media_type: media_type.map(str::to_string),
+            sender: None,
+            ack: AckStatus::Sent,
+            quoted: None,
+            reactions: Vec::new(),
+            edited: false,
+            deleted: false,
+            forwarded: false,
+        };
+
+        if let Some(quoted_id) = quoted_id {
+            msg.quoted = self.state.read().await.messages.get(chat_id)
+                .and_then(|msgs| msgs.iter().find(|m| m.id == quoted_id))
+                .map(|m| QuotedMessage {
+                    id: m.id.clone(),
+                    body: m.body.clone(),
+                    from_me: m.from_me,
+                    sender: m.sender.clone(),
+                });
+        }
+
+        self.deliver_own_message(msg).await
+    }
+
+    /// Add one of our messages to its chat and echo it back, returning its id
+    async fn deliver_own_message(&self, msg: Message) -> Result<String> {
+        {
+            let mut state = self.state.write().await;
+            let chat = state.chats.iter_mut()
+                .find(|c| c.id == msg.chat_id)
+                .ok_or_else(|| ServiceError(format!("Chat not found: {}", msg.chat_id)))?;
+            chat.last_message = Some(msg.body.clone());
+            chat.timestamp = msg.timestamp;
+            state.messages.entry(msg.chat_id.clone()).or_default().push(msg.clone());
+        }
+
+        let id = msg.id.clone();
+        let _ = self.event_tx.send(WhatsAppEvent::MessageReceived(msg)).await;
+        Ok(id)
+    }
+}
+
+#[async_trait]
+impl WhatsAppBackend for MemoryBackend {
+    fn event_sender(&self) -> mpsc::Sender<WhatsAppEvent> {
+        self.event_tx.clone()
+    }
+
+    async fn get_chats(&self) -> Result<Vec<Chat>> {
+        Ok(self.state.read().await.chats.clone())
+    }
+
+    async fn get_messages(&self, chat_id: &str, limit: usize) -> Result<Vec<Message>> {
+        let state = self.state.read().await;
+        let messages = state.messages.get(chat_id).map(Vec::as_slice).unwrap_or_default();
+        Ok(messages[messages.len().saturating_sub(limit)..].to_vec())
+    }
+
+    async fn send_message(&self, chat_id: &str, text: &str, quoted_id: Option<&str>, client_id: &str) -> Result<()> {
+        // Claimed up front, in one go with the check, so a concurrent retry can't send it too
+        if !self.state.write().await.sent.insert(client_id.to_string()) {
+            return Ok(());
+        }
+        if let Err(e) = self.store_own_message(chat_id, text, None, quoted_id).await {
+            // Not sent after all, a retry may go through
+            self.state.write().await.sent.remove(client_id);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    async fn send_media(&self, chat_id: &str, upload: &MediaUpload, caption: &str, quoted_id: Option<&str>) -> Result<()> {
+        let data = tokio::fs::read(&upload.path).await?;
+
+        // Nothing to upload to, report the whole file at once
+        let _ = self.event_tx.send(WhatsAppEvent::UploadProgress(upload.filename.clone(), upload.size, upload.size)).await;
+        let id = self.store_own_message(chat_id, caption, Some(upload.media_type()), quoted_id).await?;
+
+        self.state.write().await.media.insert(id, MediaDownload {
+            data,
+            mimetype: upload.mimetype.clone(),
+            filename: Some(upload.filename.clone()),
+        });
+        Ok(())
+    }
+
+    async fn react(&self, message_id: &str, emoji: &str) -> Result<()> {
+        let update = {
+            let mut state = self.state.write().await;
+            let msg = state.messages.values_mut()
+                .flat_map(|msgs| msgs.iter_mut())
+                .find(|m| m.id == message_id)
+                .ok_or_else(|| ServiceError(format!("Message not found: {}", message_id)))?;
+
+            let update = ReactionUpdate {
+                message_id: msg.id.clone(),
+                chat_id: msg.chat_id.clone(),
+                emoji: emoji.to_string(),
+                sender_id: "me".to_string(),
+                from_me: true,
+            };
+            msg.apply_reaction(&update);
+            update
+        };
+
+        let _ = self.event_tx.send(WhatsAppEvent::Reaction(update)).await;
+        Ok(())
+    }
+
+    async fn edit_message(&self, message_id: &str, text: &str) -> Result<()> {
+        let edit = {
+            let mut state = self.state.write().await;
+            let msg = state.messages.values_mut()
+                .flat_map(|msgs| msgs.iter_mut())
+                .find(|m| m.id == message_id)
+                .ok_or_else(|| ServiceError(format!("Message not found: {}", message_id)))?;
+            if !msg.from_me || msg.deleted {
+                return Err(ServiceError("Only your own messages can be edited".to_string()).into());
+            }
+
+            msg.body = text.to_string();
+            msg.edited = true;
+            MessageEdit {
+                id: msg.id.clone(),
+                chat_id: msg.chat_id.clone(),
+                body: msg.body.clone(),
+            }
+        };
+
+        let _ = self.event_tx.send(WhatsAppEvent::MessageEdited(edit)).await;
+        Ok(())
+    }
+
+    async fn delete_message(&self, message_id: &str, for_everyone: bool) -> Result<()> {
+        let revoke = {
+            let mut state = self.state.write().await;
+            let msgs = state.messages.values_mut()
+                .find(|msgs| msgs.iter().any(|m| m.id == message_id))
+                .ok_or_else(|| ServiceError(format!("Message not found: {}", message_id)))?;
+            let index = msgs.iter().position(|m| m.id == message_id).unwrap_or_default();
+
+            let revoke = MessageRevoke {
+                id: message_id.to_string(),
+                chat_id: msgs[index].chat_id.clone(),
+                for_everyone,
+            };
+            if for_everyone {
+                if !msgs[index].from_me {
+                    return Err(ServiceError("Only your own messages can be deleted for everyone".to_string()).into());
+                }
+                msgs[index].body.clear();
+                msgs[index].deleted = true;
+            } else {
+                msgs.remove(index);
+            }
+            revoke
+        };
+
+        let _ = self.event_tx.send(WhatsAppEvent::MessageRevoked(revoke)).await;
+        Ok(())
+    }
+
+    async fn download_media(&self, message_id: &str) -> Result<MediaDownload> {
+        let media = self.state.read().await.media.get(message_id).cloned()
+            .ok_or_else(|| anyhow!("No media stored for message {}", message_id))?;
+
+        let size = media.data.len() as u64;
+        let _ = self.event_tx.send(WhatsAppEvent::DownloadProgress(message_id.to_string(), size, size)).await;
+        Ok(media)
+    }
+
+    async fn get_profile_picture(&self, chat_id: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self.state.read().await.avatars.get(chat_id).cloned())
+    }
+
+    async fn forward_messages(&self, message_ids: &[String], chat_ids: &[String]) -> Result<()> {
+        let originals = {
+            let state = self.state.read().await;
+            if let Some(chat_id) = chat_ids.iter().find(|id| !state.chats.iter().any(|c| &c.id == *id)) {
+                return Err(ServiceError(format!("Chat not found: {}", chat_id)).into());
+            }
+            message_ids.iter()
+                .map(|id| state.messages.values()
+                    .flatten()
+                    .find(|m| &m.id == id)
+                    .cloned()
+                    .ok_or_else(|| ServiceError(format!("Message not found: {}", id))))
+                .collect::<std::result::Result<Vec<_>, _>>()?
+        };
+
+        for chat_id in chat_ids {
+            for original in &originals {
+                let copy = Message {
+                    id: Uuid::new_v4().to_string(),
+                    chat_id: chat_id.clone(),
+                    timestamp: chrono::Utc::now().timestamp(),
+                    from_me: true,
+                    sender: None,
+                    ack: AckStatus::Sent,
+                    quoted: None,
+                    reactions: Vec::new(),
+                    edited: false,
+                    forwarded: true,
+                    ..original.clone()
+                };
+                let id = self.deliver_own_message(copy).await?;
+
+                // The copy downloads like the original
+                let mut state = self.state.write().await;
+                if let Some(media) = state.media.get(&original.id).cloned() {
+                    state.media.insert(id, media);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn search_messages(&self, query: &str, limit: usize) -> Result<Vec<Message>> {
+        let query = query.to_lowercase();
+        let state = self.state.read().await;
+        let mut found: Vec<Message> = state.messages.values()
+            .flatten()
+            .filter(|m| !m.deleted && m.body.to_lowercase().contains(&query))
+            .cloned()
+            .collect();
+        found.sort_by_key(|m| std::cmp::Reverse(m.timestamp));
+        found.truncate(limit);
+        Ok(found)
+    }
+}
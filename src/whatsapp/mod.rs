@@ -1,5 +1,9 @@
+mod backend;
 mod client;
+mod memory;
 mod types;
 
+pub use backend::WhatsAppBackend;
 pub use client::WhatsAppClient;
+pub use memory::MemoryBackend;
 pub use types::*;
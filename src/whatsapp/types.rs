@@ -1,4 +1,9 @@
+use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Largest attachment we are willing to upload
+pub const MAX_MEDIA_SIZE: u64 = 100 * 1024 * 1024;
 
 /// Events received from WhatsApp service
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,10 +23,29 @@ pub enum WhatsAppEvent {
     
     #[serde(rename = "chat_updated")]
     ChatUpdated(Chat),
+
+    #[serde(rename = "message_ack")]
+    MessageAck(AckUpdate),
+
+    #[serde(rename = "reaction")]
+    Reaction(ReactionUpdate),
+
+    #[serde(rename = "message_edit")]
+    MessageEdited(MessageEdit),
+
+    #[serde(rename = "message_revoke")]
+    MessageRevoked(MessageRevoke),
     
     #[serde(rename = "disconnected")]
     Disconnected,
 
+    // Connection supervisor events
+    #[serde(skip)]
+    Reconnecting(u32),  // attempt number
+
+    #[serde(skip)]
+    Reconnected,
+
     // Internal events
     #[serde(skip)]
     ChatsLoaded(Vec<Chat>),
@@ -29,6 +53,48 @@ pub enum WhatsAppEvent {
     #[serde(skip)]
     MessagesLoaded(String, Vec<Message>),  // chat_id, messages
 
+    #[serde(skip)]
+    OutboxSent(String),  // outbox entry id
+
+    #[serde(skip)]
+    OutboxFailed(String, String, bool),  // outbox entry id, error, permanent
+
+    #[serde(skip)]
+    OutboxRetryDue,  // a failed send's backoff has passed
+
+    #[serde(skip)]
+    UploadProgress(String, u64, u64),  // filename, bytes uploaded, total bytes
+
+    #[serde(skip)]
+    MediaSent(String),  // filename
+
+    #[serde(skip)]
+    DownloadProgress(String, u64, u64),  // message id, bytes received, total bytes
+
+    #[serde(skip)]
+    MediaDownloaded(String, std::path::PathBuf, String),  // message id, saved path, sha256
+
+    #[serde(skip)]
+    DownloadFailed(String, String),  // message id, error
+
+    #[serde(skip)]
+    PreviewLoaded(String, Option<std::sync::Arc<image::RgbaImage>>),  // message id, thumbnail (None if undecodable)
+
+    #[serde(skip)]
+    ProfilePictureFetched(String, Option<Vec<u8>>),  // chat id, picture (None if it has none)
+
+    #[serde(skip)]
+    AvatarLoaded(String, Option<std::sync::Arc<image::RgbaImage>>),  // chat id, thumbnail (None if nothing to show)
+
+    #[serde(skip)]
+    SearchResults(String, Vec<Message>),  // query, messages found on the server
+
+    #[serde(skip)]
+    SearchFailed(String, String),  // query, error
+
+    #[serde(skip)]
+    SearchTyped(String),  // query, once typing pauses
+
     #[serde(skip)]
     Error(String),
 }
@@ -69,6 +135,194 @@ pub struct Message {
     
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sender: Option<String>,
+
+    #[serde(default)]
+    pub ack: AckStatus,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quoted: Option<QuotedMessage>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reactions: Vec<Reaction>,
+
+    #[serde(default)]
+    pub edited: bool,
+
+    #[serde(default)]
+    pub deleted: bool,  // Revoked for everyone, body is gone
+
+    #[serde(default)]
+    pub forwarded: bool,
+}
+
+impl Message {
+    /// Apply a reaction change; each sender holds at most one reaction per message
+    pub fn apply_reaction(&mut self, update: &ReactionUpdate) {
+        self.reactions.retain(|r| r.sender_id != update.sender_id);
+        if !update.emoji.is_empty() {
+            self.reactions.push(Reaction {
+                emoji: update.emoji.clone(),
+                sender_id: update.sender_id.clone(),
+                from_me: update.from_me,
+            });
+        }
+    }
+}
+
+/// Snippet of the message a reply quotes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotedMessage {
+    pub id: String,
+    pub body: String,
+
+    #[serde(default)]
+    pub from_me: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sender: Option<String>,
+}
+
+/// Delivery state of a message, mirroring whatsapp-web.js `ACK_*` values
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(from = "i8", into = "i8")]
+pub enum AckStatus {
+    Error,     // -1
+    #[default]
+    Pending,   // 0: not yet on the server
+    Sent,      // 1: reached the server
+    Delivered, // 2: reached the recipient's device
+    Read,      // 3
+    Played,    // 4: voice/video messages
+}
+
+impl From<i8> for AckStatus {
+    fn from(value: i8) -> Self {
+        match value {
+            i8::MIN..=-1 => AckStatus::Error,
+            0 => AckStatus::Pending,
+            1 => AckStatus::Sent,
+            2 => AckStatus::Delivered,
+            3 => AckStatus::Read,
+            _ => AckStatus::Played,
+        }
+    }
+}
+
+impl From<AckStatus> for i8 {
+    fn from(ack: AckStatus) -> Self {
+        match ack {
+            AckStatus::Error => -1,
+            AckStatus::Pending => 0,
+            AckStatus::Sent => 1,
+            AckStatus::Delivered => 2,
+            AckStatus::Read => 3,
+            AckStatus::Played => 4,
+        }
+    }
+}
+
+/// Ack change for a message we sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AckUpdate {
+    pub id: String,
+    pub chat_id: String,
+    pub ack: AckStatus,
+}
+
+/// Emoji reaction left on a message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reaction {
+    pub emoji: String,
+    pub sender_id: String,
+
+    #[serde(default)]
+    pub from_me: bool,
+}
+
+/// Reaction added, changed or removed (empty `emoji`) on a message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReactionUpdate {
+    pub message_id: String,
+    pub chat_id: String,
+    pub emoji: String,
+    pub sender_id: String,
+
+    #[serde(default)]
+    pub from_me: bool,
+}
+
+/// New text for an edited message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageEdit {
+    pub id: String,
+    pub chat_id: String,
+    pub body: String,
+}
+
+/// Message deleted for everyone, or only for us (`for_everyone: false`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageRevoke {
+    pub id: String,
+    pub chat_id: String,
+
+    #[serde(default)]
+    pub for_everyone: bool,
+}
+
+/// Local file to send as a media message
+#[derive(Debug, Clone)]
+pub struct MediaUpload {
+    pub path: PathBuf,
+    pub filename: String,
+    pub mimetype: String,
+    pub size: u64,
+}
+
+impl MediaUpload {
+    /// Inspect a local file, guessing its MIME type from the extension
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let metadata = std::fs::metadata(path)
+            .with_context(|| format!("Cannot read {}", path.display()))?;
+        if !metadata.is_file() {
+            bail!("{} is not a file", path.display());
+        }
+        if metadata.len() > MAX_MEDIA_SIZE {
+            bail!("{} is larger than {} MB", path.display(), MAX_MEDIA_SIZE / 1024 / 1024);
+        }
+
+        let filename = path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "file".to_string());
+        let mimetype = mime_guess::from_path(path)
+            .first_or_octet_stream()
+            .essence_str()
+            .to_string();
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            filename,
+            mimetype,
+            size: metadata.len(),
+        })
+    }
+
+    /// WhatsApp message type the upload turns into
+    pub fn media_type(&self) -> &'static str {
+        match self.mimetype.split('/').next() {
+            Some("image") => "image",
+            Some("video") => "video",
+            Some("audio") => "audio",
+            _ => "document",
+        }
+    }
+}
+
+/// Media fetched from the service
+#[derive(Debug, Clone)]
+pub struct MediaDownload {
+    pub data: Vec<u8>,
+    pub mimetype: String,
+    pub filename: Option<String>,  // Original name, documents usually have one
 }
 
 /// Request to WhatsApp service
@@ -90,3 +344,17 @@ pub struct WhatsAppResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
 }
+
+/// Error reported by the service itself, as opposed to a transport failure
+///
+/// Retrying these is pointless: the request reached the service and was rejected.
+#[derive(Debug)]
+pub struct ServiceError(pub String);
+
+impl std::fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WhatsApp service error: {}", self.0)
+    }
+}
+
+impl std::error::Error for ServiceError {}
@@ -0,0 +1,165 @@
+//! Drives the app against the in-memory backend: the demo chats load, a
+//! message goes out through the outbox, an incoming one shows up and a
+//! refresh keeps older history in view.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{backend::TestBackend, Terminal};
+use serde_json::json;
+use tokio::sync::mpsc;
+
+use zaptui::config::Config;
+use zaptui::ui::{App, ImageProtocol};
+use zaptui::whatsapp::{MemoryBackend, Message, WhatsAppBackend, WhatsAppEvent};
+
+const ALICE: &str = "5511999990001@c.us";
+
+struct Harness {
+    app: App,
+    backend: Arc<MemoryBackend>,
+    events: mpsc::Receiver<WhatsAppEvent>,
+    terminal: Terminal<TestBackend>,
+}
+
+impl Harness {
+    async fn start() -> Self {
+        let (event_tx, events) = mpsc::channel(100);
+        let backend = Arc::new(MemoryBackend::demo(event_tx));
+        backend.start().await.unwrap();
+
+        // No store: nothing is read from or written to the user's cache
+        let app = App::new(Config::default(), backend.clone(), None, ImageProtocol::HalfBlocks);
+        let mut harness = Self {
+            app,
+            backend,
+            events,
+            terminal: Terminal::new(TestBackend::new(100, 24)).unwrap(),
+        };
+        harness.settle().await;
+        harness
+    }
+
+    /// Handle events until the backend and the app's background tasks go quiet
+    async fn settle(&mut self) {
+        while let Ok(Some(event)) = tokio::time::timeout(Duration::from_millis(100), self.events.recv()).await {
+            self.app.handle_whatsapp_event(event).await.unwrap();
+        }
+    }
+
+    async fn key(&mut self, code: KeyCode) {
+        self.key_with(code, KeyModifiers::NONE).await;
+    }
+
+    async fn key_with(&mut self, code: KeyCode, modifiers: KeyModifiers) {
+        // Like the main loop, the app has drawn a frame (and knows its layout) before input arrives
+        self.screen();
+        let quit = self.app.handle_event(Event::Key(KeyEvent::new(code, modifiers))).await.unwrap();
+        assert!(!quit);
+        self.settle().await;
+    }
+
+    async fn type_text(&mut self, text: &str) {
+        for c in text.chars() {
+            self.key(KeyCode::Char(c)).await;
+        }
+    }
+
+    /// The rendered frame, one string per row
+    fn screen(&mut self) -> Vec<String> {
+        let app = &mut self.app;
+        let frame = self.terminal.draw(|frame| app.render(frame)).unwrap();
+        let area = frame.area;
+        (area.top()..area.bottom())
+            .map(|y| (area.left()..area.right()).map(|x| frame.buffer[(x, y)].symbol()).collect())
+            .collect()
+    }
+
+    fn screen_contains(&mut self, text: &str) -> bool {
+        self.screen().iter().any(|row| row.contains(text))
+    }
+
+    /// Open Alice's chat, the first one below "Archived Messages"
+    async fn open_alice(&mut self) {
+        self.key(KeyCode::Down).await;
+        self.key(KeyCode::Enter).await;
+        assert!(self.screen_contains("Perfect, see you there"), "{:#?}", self.screen());
+    }
+}
+
+#[tokio::test]
+async fn loads_demo_chats() {
+    let mut harness = Harness::start().await;
+
+    for name in ["Alice", "Weekend Hike", "Bob"] {
+        assert!(harness.screen_contains(name), "{} missing: {:#?}", name, harness.screen());
+    }
+    // Archived chats stay behind their own row
+    assert!(!harness.screen_contains("Old Project"));
+}
+
+#[tokio::test]
+async fn sends_through_the_outbox() {
+    let mut harness = Harness::start().await;
+    harness.open_alice().await;
+
+    harness.type_text("On my way").await;
+    harness.key(KeyCode::Enter).await;
+
+    let sent: Vec<Message> = harness.backend.get_messages(ALICE, 100).await.unwrap()
+        .into_iter()
+        .filter(|m| m.from_me && m.body == "On my way")
+        .collect();
+    assert_eq!(sent.len(), 1);
+    assert!(harness.screen_contains("Me: On my way"), "{:#?}", harness.screen());
+    assert!(harness.screen_contains("Message sent"), "{:#?}", harness.screen());
+}
+
+fn message_from_alice(id: &str, body: &str, timestamp: i64) -> Message {
+    serde_json::from_value(json!({
+        "id": id,
+        "chat_id": ALICE,
+        "body": body,
+        "timestamp": timestamp,
+        "from_me": false,
+        "sender": "Alice",
+    }))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn shows_incoming_messages() {
+    let mut harness = Harness::start().await;
+    harness.open_alice().await;
+
+    let message = message_from_alice("incoming-1", "Running 5 minutes late", chrono::Utc::now().timestamp());
+    harness.app.handle_whatsapp_event(WhatsAppEvent::MessageReceived(message)).await.unwrap();
+    harness.settle().await;
+
+    assert!(harness.screen_contains("Alice: Running 5 minutes late"), "{:#?}", harness.screen());
+}
+
+#[tokio::test]
+async fn refresh_keeps_an_opened_search_result() {
+    let mut harness = Harness::start().await;
+    harness.open_alice().await;
+
+    // A hit older than anything the chat loads, as a server search finds them
+    harness.key_with(KeyCode::Char('f'), KeyModifiers::CONTROL).await;
+    harness.type_text("ancient").await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    harness.settle().await;
+    let hit = message_from_alice("old-1", "Ancient history", 1_000_000_000);
+    harness.app.handle_whatsapp_event(WhatsAppEvent::SearchResults("ancient".to_string(), vec![hit])).await.unwrap();
+    harness.key(KeyCode::Enter).await;
+    assert!(harness.screen_contains("Ancient history"), "{:#?}", harness.screen());
+
+    harness.app.refresh_current_chat_messages().await.unwrap();
+    harness.settle().await;
+
+    assert!(harness.screen_contains("Ancient history"), "{:#?}", harness.screen());
+    // Still selected: replying picks it up
+    harness.key(KeyCode::Char('r')).await;
+    assert!(harness.screen_contains("Replying to Alice: Ancient history"), "{:#?}", harness.screen());
+}
@@ -0,0 +1,134 @@
+//! Runs `zaptui-mock-service` and talks to it with the real `WhatsAppClient`,
+//! over the same protocol as the Node service, through disconnects too.
+
+use std::net::TcpListener;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use assert_cmd::cargo::{cargo_bin, cargo_bin_cmd};
+use predicates::prelude::*;
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+
+use zaptui::whatsapp::{WhatsAppBackend, WhatsAppClient, WhatsAppEvent};
+
+const ALICE: &str = "5511999990001@c.us";
+
+/// A mock service on a port of its own, killed when dropped
+struct MockService {
+    child: Child,
+    url: String,
+    _fixture: tempfile::NamedTempFile,
+}
+
+impl MockService {
+    fn start(fixture: Value) -> Self {
+        // Free right now, so most likely still free when the mock binds it
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        serde_json::to_writer(&file, &fixture).unwrap();
+
+        let child = Command::new(cargo_bin!("zaptui-mock-service"))
+            .arg("--port")
+            .arg(port.to_string())
+            .arg("--fixture")
+            .arg(file.path())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        Self { child, url: format!("ws://127.0.0.1:{}", port), _fixture: file }
+    }
+
+    /// Connect as soon as the service listens
+    async fn connect(&self, event_tx: mpsc::Sender<WhatsAppEvent>) -> WhatsAppClient {
+        for _ in 0..50 {
+            if let Ok(client) = WhatsAppClient::connect(&self.url, event_tx.clone()).await {
+                return client;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("Mock service not listening on {}", self.url);
+    }
+}
+
+impl Drop for MockService {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Wait for the first event `matches` accepts, skipping the others
+async fn expect_event(events: &mut mpsc::Receiver<WhatsAppEvent>, matches: impl Fn(&WhatsAppEvent) -> bool) -> WhatsAppEvent {
+    let wait = async {
+        loop {
+            let event = events.recv().await.expect("event channel closed");
+            if matches(&event) {
+                return event;
+            }
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(5), wait).await.expect("event not received in time")
+}
+
+fn fixture() -> Value {
+    json!({
+        "chats": [{ "id": ALICE, "name": "Alice", "is_group": false, "timestamp": -60 }],
+        "messages": [
+            { "id": "m1", "chat_id": ALICE, "body": "Lunch?", "timestamp": -600, "from_me": false, "sender": "Alice" }
+        ],
+    })
+}
+
+#[tokio::test]
+async fn serves_the_fixture() {
+    let service = MockService::start(fixture());
+    let (event_tx, mut events) = mpsc::channel(100);
+    let client = service.connect(event_tx).await;
+    expect_event(&mut events, |e| matches!(e, WhatsAppEvent::Ready)).await;
+
+    let chats = client.get_chats().await.unwrap();
+    assert_eq!(chats.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), ["Alice"]);
+    let messages = client.get_messages(ALICE, 50).await.unwrap();
+    assert_eq!(messages.iter().map(|m| m.body.as_str()).collect::<Vec<_>>(), ["Lunch?"]);
+
+    // Sent messages come back like message_create, a retry doesn't send again
+    client.send_message(ALICE, "Sure", None, "entry-1").await.unwrap();
+    client.send_message(ALICE, "Sure", None, "entry-1").await.unwrap();
+    let echo = expect_event(&mut events, |e| matches!(e, WhatsAppEvent::MessageReceived(_))).await;
+    assert!(matches!(echo, WhatsAppEvent::MessageReceived(m) if m.from_me && m.body == "Sure"));
+    let sent = client.get_messages(ALICE, 50).await.unwrap().into_iter().filter(|m| m.body == "Sure").count();
+    assert_eq!(sent, 1);
+}
+
+#[tokio::test]
+async fn reconnects_after_a_disconnect() {
+    let mut fixture = fixture();
+    fixture["response_delay_ms"] = json!(1500);
+    fixture["script"] = json!([{ "after_ms": 200, "action": "disconnect" }]);
+    let service = MockService::start(fixture);
+    let (event_tx, mut events) = mpsc::channel(100);
+    let client = service.connect(event_tx).await;
+
+    // In flight when the connection drops: fails right away instead of timing out
+    let started = Instant::now();
+    let error = client.get_chats().await.unwrap_err();
+    assert!(format!("{:#}", error).contains("Connection to WhatsApp service lost"), "{:#}", error);
+    assert!(started.elapsed() < Duration::from_millis(1500));
+
+    expect_event(&mut events, |e| matches!(e, WhatsAppEvent::Disconnected)).await;
+    expect_event(&mut events, |e| matches!(e, WhatsAppEvent::Reconnecting(1))).await;
+    expect_event(&mut events, |e| matches!(e, WhatsAppEvent::Reconnected)).await;
+
+    // The same client carries on over the new connection
+    assert_eq!(client.get_chats().await.unwrap().len(), 1);
+}
+
+#[test]
+fn fails_on_a_missing_fixture() {
+    cargo_bin_cmd!("zaptui-mock-service")
+        .args(["--fixture", "/nonexistent/fixture.json"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to read fixture"));
+}
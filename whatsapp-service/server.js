@@ -1,11 +1,27 @@
-const { Client, LocalAuth } = require("whatsapp-web.js");
+const fs = require("fs");
+const path = require("path");
+const { Client, LocalAuth, MessageMedia } = require("whatsapp-web.js");
 const WebSocket = require("ws");
 
+const AUTH_PATH = process.env.ZAPTUI_AUTH_PATH || "../.wwebjs_auth";
+// Sent clientIds, kept across restarts so a retry after one isn't sent twice
+const SENT_PATH = path.join(AUTH_PATH, "sent-messages.json");
+// How long a sent clientId is remembered
+const SENT_TTL_MS = 60 * 60 * 1000;
+
 class WhatsAppService {
   constructor() {
     this.client = null;
     this.wss = null;
     this.clients = new Set();
+    // In-progress media uploads, streamed in chunks by uploadId
+    this.uploads = new Map();
+    // Downloaded media waiting to be fetched in chunks, by downloadId
+    this.downloads = new Map();
+    // Sends in flight by the client's message id, so retries aren't sent twice
+    this.sending = new Map();
+    // When each clientId was sent, persisted in SENT_PATH
+    this.sentAt = this.loadSentMessages();
     this.initializeClient();
     this.initializeWebSocket();
   }
@@ -15,7 +31,7 @@ class WhatsAppService {
 
     this.client = new Client({
       authStrategy: new LocalAuth({
-        dataPath: process.env.ZAPTUI_AUTH_PATH || "../.wwebjs_auth",
+        dataPath: AUTH_PATH,
       }),
       puppeteer: {
         headless: true,
@@ -68,6 +84,65 @@ class WhatsAppService {
       }
     });
 
+    this.client.on("message_ack", (msg, ack) => {
+      this.broadcast({
+        event: "message_ack",
+        data: {
+          id: msg.id._serialized,
+          chat_id: msg.fromMe ? msg.to : msg.from,
+          ack,
+        },
+      });
+    });
+
+    this.client.on("message_reaction", (reaction) => {
+      this.broadcast({
+        event: "reaction",
+        data: {
+          message_id: reaction.msgId._serialized,
+          chat_id: reaction.msgId.remote,
+          // Empty when the reaction was removed
+          emoji: reaction.reaction || "",
+          sender_id: reaction.senderId,
+          from_me: reaction.id.fromMe,
+        },
+      });
+    });
+
+    this.client.on("message_edit", (msg, newBody) => {
+      this.broadcast({
+        event: "message_edit",
+        data: {
+          id: msg.id._serialized,
+          chat_id: msg.fromMe ? msg.to : msg.from,
+          body: newBody || "",
+        },
+      });
+    });
+
+    this.client.on("message_revoke_everyone", (after, before) => {
+      const msg = before || after;
+      this.broadcast({
+        event: "message_revoke",
+        data: {
+          id: msg.id._serialized,
+          chat_id: msg.fromMe ? msg.to : msg.from,
+          for_everyone: true,
+        },
+      });
+    });
+
+    this.client.on("message_revoke_me", (msg) => {
+      this.broadcast({
+        event: "message_revoke",
+        data: {
+          id: msg.id._serialized,
+          chat_id: msg.fromMe ? msg.to : msg.from,
+          for_everyone: false,
+        },
+      });
+    });
+
     this.client.on("disconnected", (reason) => {
       console.log("Client disconnected:", reason);
       this.broadcast({
@@ -103,7 +178,7 @@ class WhatsAppService {
       ws.on("message", async (data) => {
         try {
           const request = JSON.parse(data);
-          const response = await this.handleRequest(request);
+          const response = await this.handleRequest(request, ws);
           ws.send(JSON.stringify(response));
         } catch (err) {
           console.error("Error handling request:", err);
@@ -119,6 +194,7 @@ class WhatsAppService {
       ws.on("close", () => {
         console.log("Client disconnected");
         this.clients.delete(ws);
+        this.dropUploads(ws);
       });
 
       ws.on("error", (err) => {
@@ -128,7 +204,7 @@ class WhatsAppService {
     });
   }
 
-  async handleRequest(req) {
+  async handleRequest(req, ws) {
     const { id, method, params } = req;
 
     try {
@@ -144,13 +220,69 @@ class WhatsAppService {
           break;
 
         case "sendMessage":
-          result = await this.sendMessage(params.chatId, params.text);
+          result = await this.sendMessage(
+            params.chatId,
+            params.text,
+            params.quotedMessageId,
+            params.clientId,
+          );
+          break;
+
+        case "sendMediaStart":
+          result = this.sendMediaStart(params, ws);
+          break;
+
+        case "sendMediaChunk":
+          result = this.sendMediaChunk(
+            params.uploadId,
+            params.offset,
+            params.data,
+          );
+          break;
+
+        case "sendMediaFinish":
+          result = await this.sendMediaFinish(params.uploadId);
+          break;
+
+        case "react":
+          result = await this.react(params.messageId, params.emoji);
+          break;
+
+        case "editMessage":
+          result = await this.editMessage(params.messageId, params.text);
+          break;
+
+        case "deleteMessage":
+          result = await this.deleteMessage(params.messageId, params.everyone);
+          break;
+
+        case "forwardMessages":
+          result = await this.forwardMessages(
+            params.messageIds,
+            params.chatIds,
+          );
           break;
 
         case "downloadMedia":
           result = await this.downloadMedia(params.messageId);
           break;
 
+        case "downloadMediaChunk":
+          result = this.downloadMediaChunk(
+            params.downloadId,
+            params.offset,
+            params.length,
+          );
+          break;
+
+        case "getProfilePicture":
+          result = await this.getProfilePicture(params.chatId);
+          break;
+
+        case "searchMessages":
+          result = await this.searchMessages(params.query, params.limit);
+          break;
+
         default:
           throw new Error(`Unknown method: ${method}`);
       }
@@ -181,15 +313,244 @@ class WhatsAppService {
     return Promise.all(messages.map((msg) => this.serializeMessage(msg)));
   }
 
-  async sendMessage(chatId, text) {
-    await this.client.sendMessage(chatId, text);
+  async sendMessage(chatId, text, quotedMessageId, clientId) {
+    // A retry of a message we already sent (or are sending) gets the same outcome
+    if (clientId && Date.now() - this.sentAt.get(clientId) < SENT_TTL_MS) {
+      return { success: true };
+    }
+    const inFlight = clientId && this.sending.get(clientId);
+    if (inFlight) {
+      return inFlight;
+    }
+
+    const options = quotedMessageId ? { quotedMessageId } : {};
+    const sending = this.client
+      .sendMessage(chatId, text, options)
+      .then(() => ({ success: true }));
+    if (clientId) {
+      this.sending.set(clientId, sending);
+      // Failed sends may be retried for real, so only successes are remembered
+      sending
+        .then(() => this.recordSent(clientId), () => {})
+        .finally(() => this.sending.delete(clientId));
+    }
+    return sending;
+  }
+
+  // Sent clientIds still within SENT_TTL_MS, from before a restart
+  loadSentMessages() {
+    try {
+      const saved = JSON.parse(fs.readFileSync(SENT_PATH, "utf8"));
+      return new Map(
+        Object.entries(saved).filter(([, at]) => Date.now() - at < SENT_TTL_MS),
+      );
+    } catch (error) {
+      if (error.code !== "ENOENT") {
+        console.error("Cannot read sent messages:", error.message);
+      }
+      return new Map();
+    }
+  }
+
+  recordSent(clientId) {
+    const now = Date.now();
+    this.sentAt.set(clientId, now);
+    for (const [id, at] of this.sentAt) {
+      if (now - at >= SENT_TTL_MS) {
+        this.sentAt.delete(id);
+      }
+    }
+
+    // Written before the client hears back, so a retry after a restart finds it
+    try {
+      fs.mkdirSync(AUTH_PATH, { recursive: true });
+      fs.writeFileSync(SENT_PATH, JSON.stringify(Object.fromEntries(this.sentAt)));
+    } catch (error) {
+      console.error("Cannot save sent messages:", error.message);
+    }
+  }
+
+  sendMediaStart(params, ws) {
+    const uploadId = params.uploadId;
+    this.uploads.set(uploadId, {
+      ws,
+      // Drop abandoned uploads after a while
+      timer: setTimeout(() => this.uploads.delete(uploadId), 10 * 60 * 1000),
+      chatId: params.chatId,
+      filename: params.filename,
+      mimetype: params.mimetype,
+      caption: params.caption || "",
+      quotedMessageId: params.quotedMessageId,
+      chunks: [],
+      received: 0,
+    });
+    return { success: true };
+  }
+
+  sendMediaChunk(uploadId, offset, data) {
+    const upload = this.uploads.get(uploadId);
+    if (!upload) {
+      throw new Error(`Unknown upload: ${uploadId}`);
+    }
+    try {
+      if (offset !== upload.received) {
+        throw new Error("Chunk out of order");
+      }
+      const chunk = Buffer.from(data, "base64");
+      upload.chunks.push(chunk);
+      upload.received += chunk.length;
+      return { received: upload.received };
+    } catch (err) {
+      // The client gives up on a failed upload, so don't keep its chunks
+      this.deleteUpload(uploadId);
+      throw err;
+    }
+  }
+
+  deleteUpload(uploadId) {
+    const upload = this.uploads.get(uploadId);
+    if (upload) {
+      clearTimeout(upload.timer);
+      this.uploads.delete(uploadId);
+    }
+  }
+
+  // Uploads from a closed connection can never finish
+  dropUploads(ws) {
+    for (const [uploadId, upload] of this.uploads) {
+      if (upload.ws === ws) {
+        this.deleteUpload(uploadId);
+      }
+    }
+  }
+
+  async sendMediaFinish(uploadId) {
+    const upload = this.uploads.get(uploadId);
+    if (!upload) {
+      throw new Error(`Unknown upload: ${uploadId}`);
+    }
+    this.deleteUpload(uploadId);
+
+    const media = new MessageMedia(
+      upload.mimetype,
+      Buffer.concat(upload.chunks).toString("base64"),
+      upload.filename,
+    );
+    const options = { caption: upload.caption };
+    if (upload.quotedMessageId) {
+      options.quotedMessageId = upload.quotedMessageId;
+    }
+    await this.client.sendMessage(upload.chatId, media, options);
+    return { success: true };
+  }
+
+  async react(messageId, emoji) {
+    const msg = await this.client.getMessageById(messageId);
+    if (!msg) {
+      throw new Error(`Message not found: ${messageId}`);
+    }
+    await msg.react(emoji);
+    return { success: true };
+  }
+
+  async editMessage(messageId, text) {
+    const msg = await this.client.getMessageById(messageId);
+    if (!msg) {
+      throw new Error(`Message not found: ${messageId}`);
+    }
+    // Resolves to null when WhatsApp refuses the edit (not ours, too old)
+    const edited = await msg.edit(text);
+    if (!edited) {
+      throw new Error("Message can no longer be edited");
+    }
+    return { success: true };
+  }
+
+  async deleteMessage(messageId, everyone) {
+    const msg = await this.client.getMessageById(messageId);
+    if (!msg) {
+      throw new Error(`Message not found: ${messageId}`);
+    }
+    await msg.delete(!!everyone);
+    return { success: true };
+  }
+
+  async forwardMessages(messageIds, chatIds) {
+    // Look them all up first so a missing one forwards nothing
+    const messages = [];
+    for (const messageId of messageIds) {
+      const msg = await this.client.getMessageById(messageId);
+      if (!msg) {
+        throw new Error(`Message not found: ${messageId}`);
+      }
+      messages.push(msg);
+    }
+    // One chat at a time, so messages arrive in their original order
+    for (const chatId of chatIds) {
+      for (const msg of messages) {
+        await msg.forward(chatId);
+      }
+    }
     return { success: true };
   }
 
   async downloadMedia(messageId) {
-    // This would need to be implemented with message lookup
-    // For now, return placeholder
-    return null;
+    const msg = await this.client.getMessageById(messageId);
+    if (!msg || !msg.hasMedia) {
+      throw new Error(`No media for message ${messageId}`);
+    }
+    const media = await msg.downloadMedia();
+    if (!media) {
+      throw new Error("Media is no longer available on the phone");
+    }
+
+    const data = Buffer.from(media.data, "base64");
+    const downloadId = `${messageId}-${Date.now()}`;
+    this.downloads.set(downloadId, data);
+    // Drop abandoned downloads after a while
+    setTimeout(() => this.downloads.delete(downloadId), 10 * 60 * 1000);
+
+    return {
+      downloadId,
+      size: data.length,
+      mimetype: media.mimetype,
+      filename: media.filename || null,
+    };
+  }
+
+  downloadMediaChunk(downloadId, offset, length) {
+    const data = this.downloads.get(downloadId);
+    if (!data) {
+      throw new Error(`Unknown download: ${downloadId}`);
+    }
+    const end = Math.min(offset + length, data.length);
+    if (end >= data.length) {
+      this.downloads.delete(downloadId);
+    }
+    return { data: data.subarray(offset, end).toString("base64") };
+  }
+
+  async getProfilePicture(chatId) {
+    // Undefined when there is no picture or privacy settings hide it
+    const url = await this.client.getProfilePicUrl(chatId);
+    if (!url) {
+      return { data: null };
+    }
+
+    const response = await fetch(url);
+    if (!response.ok) {
+      throw new Error(`Profile picture fetch failed: HTTP ${response.status}`);
+    }
+    const data = Buffer.from(await response.arrayBuffer());
+    return {
+      data: data.toString("base64"),
+      mimetype: response.headers.get("content-type"),
+    };
+  }
+
+  async searchMessages(query, limit = 50) {
+    const messages = await this.client.searchMessages(query, { limit });
+    return Promise.all(messages.map((msg) => this.serializeMessage(msg)));
   }
 
   serializeChat(chat) {
@@ -226,15 +587,55 @@ class WhatsAppService {
       }
     }
 
+    let quoted = null;
+    if (msg.hasQuotedMsg) {
+      try {
+        const q = await msg.getQuotedMessage();
+        quoted = {
+          id: q.id._serialized,
+          body: q.body || "",
+          from_me: q.fromMe,
+          sender: q.fromMe
+            ? null
+            : q._data?.notifyName || q.author?.split("@")[0] || null,
+        };
+      } catch (e) {
+        // Quoted message may no longer be available
+      }
+    }
+
+    let reactions = [];
+    if (msg.hasReaction) {
+      try {
+        const lists = (await msg.getReactions()) || [];
+        reactions = lists.flatMap((list) =>
+          list.senders.map((r) => ({
+            emoji: r.reaction,
+            sender_id: r.senderId,
+            from_me: r.id.fromMe,
+          })),
+        );
+      } catch (e) {
+        // Reactions are optional, the message is still useful without them
+      }
+    }
+
     return {
       id: msg.id._serialized,
-      chat_id: msg.from || msg.to,
+      // Own messages are "from" us, so their chat is the recipient
+      chat_id: msg.fromMe ? msg.to : msg.from,
       body: msg.body || "",
       timestamp: msg.timestamp,
       from_me: msg.fromMe,
       has_media: msg.hasMedia,
       media_type: msg.type,
       sender,
+      ack: msg.ack ?? 0,
+      quoted,
+      reactions,
+      edited: !!msg._data?.latestEditMsgKey,
+      forwarded: !!msg.isForwarded,
+      deleted: msg.type === "revoked",
     };
   }
 